@@ -0,0 +1,104 @@
+//! Compares stepping Life on bit-packed rows, serially and in parallel bands, with stepping it a
+//! byte per cell and with the original bool per cell step
+
+use std::str::FromStr;
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use game_of_life::bitgrid::BitGrid;
+use game_of_life::executor::Threads;
+use game_of_life::rule::{LifeLike, Rule};
+use game_of_life::state;
+use game_of_life::topology::Topology;
+use modulo::Mod;
+
+const SIZES: [usize; 3] = [256, 1024, 4096];
+
+/// A third of the cells alive, the same ones on every run
+fn soup(size: usize) -> Vec<(usize, usize)> {
+    let mut seed = 0x2545_f491_4f6c_dd1d_u64;
+    let mut cells = Vec::new();
+    for x in 0..size {
+        for y in 0..size {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            if seed % 3 == 0 {
+                cells.push((x, y));
+            }
+        }
+    }
+    cells
+}
+
+const NEIGHBORS: [[i32; 2]; 8] = [
+    [-1, -1],
+    [-1, 0],
+    [-1, 1],
+    [0, -1],
+    [0, 1],
+    [1, -1],
+    [1, 0],
+    [1, 1],
+];
+
+/// The step the app started with: Life on a torus of bools, wrapping every neighbor with a modulo
+fn step_bools(cells: &mut [Vec<bool>]) {
+    let initial_state_grid = cells.to_vec();
+    let (width, height) = (cells.len(), cells[0].len());
+    for x in 0..width {
+        for y in 0..height {
+            let mut num_alive_nb = 0;
+            for [dx, dy] in NEIGHBORS {
+                let nx = (dx + x as i32).modulo(width as i32) as usize;
+                let ny = (dy + y as i32).modulo(height as i32) as usize;
+                if initial_state_grid[nx][ny] {
+                    num_alive_nb += 1;
+                }
+            }
+            let alive = initial_state_grid[x][y];
+            cells[x][y] = num_alive_nb == 3 || (alive && num_alive_nb == 2);
+        }
+    }
+}
+
+fn life() -> LifeLike {
+    match Rule::from_str("B3/S23") {
+        Ok(Rule::LifeLike(rule)) => rule,
+        _ => unreachable!(),
+    }
+}
+
+fn step(c: &mut Criterion) {
+    let rule = life();
+    let threads = Threads::default();
+    let mut group = c.benchmark_group("torus");
+    group.sample_size(10);
+    for size in SIZES {
+        let cells = soup(size);
+        let mut bits = BitGrid::new(size, size);
+        let mut bytes = vec![vec![0; size]; size];
+        let mut bools = vec![vec![false; size]; size];
+        for &(x, y) in &cells {
+            bits.set(x, y, true);
+            bytes[x][y] = 1;
+            bools[x][y] = true;
+        }
+        group.bench_function(BenchmarkId::new("bits", size), |b| {
+            b.iter(|| bits.step(&rule, Topology::Torus))
+        });
+        let mut bands = bits.clone();
+        group.bench_function(BenchmarkId::new("bands", size), |b| {
+            b.iter(|| bands.step_parallel(&rule, Topology::Torus, &threads))
+        });
+        group.bench_function(BenchmarkId::new("bytes", size), |b| {
+            b.iter(|| state::step_life_like(&mut bytes, &rule, Topology::Torus))
+        });
+        group.bench_function(BenchmarkId::new("bools", size), |b| {
+            b.iter(|| step_bools(&mut bools))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, step);
+criterion_main!(benches);
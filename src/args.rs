@@ -0,0 +1,174 @@
+use std::error::Error;
+use std::fmt;
+use std::path::PathBuf;
+
+use game_of_life::state::GridSize;
+use game_of_life::topology::Topology;
+
+use crate::startup::Engine;
+
+pub const USAGE: &str = "\
+Usage: game_of_life [OPTIONS] [PATTERN]
+
+Opens PATTERN, or the last opened pattern or `pattern.rle` once Ctrl+O is pressed. Ctrl+S
+saves to the same file. Options win over the config file.
+
+Options:
+  -r, --rule <RULE>        A rulestring or the name of a .rule file, instead of the pattern's rule
+  -e, --engine <ENGINE>    bounded, unbounded or hashlife
+  -s, --size <WxH>         The size of the bounded grid, like 200x100
+  -t, --topology <NAME>    How the edges of the bounded grid are joined: torus, plane,
+                           vertical-cylinder, horizontal-cylinder, klein-bottle,
+                           klein-bottle-y or cross-surface
+      --seed <N>           Fill the board with a random soup from this seed
+      --speed <SECONDS>    The time between updates, 0.1 unless the config sets it
+      --run                Start running instead of paused
+      --history-memory <MIB>
+                           The memory undo may use, 64 unless the config sets it
+      --window <WxH>       The size of the window in pixels, like 800x600
+      --headless           Run without a window and print the result
+  -g, --generations <N>    How many generations to run headless
+  -o, --output <FILE>      Save the cells after a headless run, in the format of the extension
+  -h, --help               Print this help";
+
+/// The options given on the command line
+#[derive(Default, Debug)]
+pub struct Options {
+    pub pattern: Option<PathBuf>,
+    pub headless: bool,
+    pub generations: u64,
+    pub rule: Option<String>,
+    pub engine: Option<Engine>,
+    pub size: Option<GridSize>,
+    pub topology: Option<Topology>,
+    pub seed: Option<u64>,
+    pub speed: Option<f32>,
+    pub run: bool,
+    pub history_memory: Option<usize>,
+    pub window: Option<(f32, f32)>,
+    pub output: Option<PathBuf>,
+    pub help: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ArgsError {
+    UnknownOption(String),
+    MissingValue(String),
+    InvalidValue { option: String, value: String },
+    UnexpectedArgument(String),
+    MissingPattern,
+}
+
+impl fmt::Display for ArgsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::UnknownOption(option) => write!(f, "unknown option '{}'", option),
+            Self::MissingValue(option) => write!(f, "'{}' needs a value", option),
+            Self::InvalidValue { option, value } => {
+                write!(f, "'{}' is not a valid value for '{}'", value, option)
+            }
+            Self::UnexpectedArgument(arg) => write!(f, "unexpected argument '{}'", arg),
+            Self::MissingPattern => write!(f, "--headless needs a pattern file or a seed"),
+        }
+    }
+}
+
+impl Error for ArgsError {}
+
+impl Options {
+    /// Parses the arguments after the program name
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Self, ArgsError> {
+        let mut options = Self::default();
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--headless" => options.headless = true,
+                "-g" | "--generations" => {
+                    let value = value(&arg, args.next())?;
+                    options.generations = value.parse().map_err(|_| invalid(&arg, value))?;
+                }
+                "-r" | "--rule" => options.rule = Some(value(&arg, args.next())?),
+                "-e" | "--engine" => {
+                    let value = value(&arg, args.next())?;
+                    options.engine = Some(value.parse().map_err(|_| invalid(&arg, value))?);
+                }
+                "-s" | "--size" => {
+                    let value = value(&arg, args.next())?;
+                    let (width, height) = parse_size(&value).ok_or_else(|| invalid(&arg, value))?;
+                    options.size = Some(GridSize { width, height });
+                }
+                "-t" | "--topology" => {
+                    let value = value(&arg, args.next())?;
+                    options.topology =
+                        Some(parse_topology(&value).ok_or_else(|| invalid(&arg, value))?);
+                }
+                "--seed" => {
+                    let value = value(&arg, args.next())?;
+                    options.seed = Some(value.parse().map_err(|_| invalid(&arg, value))?);
+                }
+                "--speed" => {
+                    let value = value(&arg, args.next())?;
+                    let speed = value
+                        .parse()
+                        .ok()
+                        .filter(|speed: &f32| *speed >= 0.0 && speed.is_finite());
+                    options.speed = Some(speed.ok_or_else(|| invalid(&arg, value))?);
+                }
+                "--run" => options.run = true,
+                "--history-memory" => {
+                    let value = value(&arg, args.next())?;
+                    options.history_memory = Some(value.parse().map_err(|_| invalid(&arg, value))?);
+                }
+                "--window" => {
+                    let value = value(&arg, args.next())?;
+                    let (width, height) = parse_size(&value).ok_or_else(|| invalid(&arg, value))?;
+                    options.window = Some((width as f32, height as f32));
+                }
+                "-o" | "--output" => options.output = Some(value(&arg, args.next())?.into()),
+                "-h" | "--help" => options.help = true,
+                _ if arg.starts_with('-') => return Err(ArgsError::UnknownOption(arg)),
+                _ if options.pattern.is_none() => options.pattern = Some(arg.into()),
+                _ => return Err(ArgsError::UnexpectedArgument(arg)),
+            }
+        }
+        if options.headless && options.pattern.is_none() && options.seed.is_none() && !options.help
+        {
+            return Err(ArgsError::MissingPattern);
+        }
+        Ok(options)
+    }
+}
+
+fn value(option: &str, value: Option<String>) -> Result<String, ArgsError> {
+    value.ok_or_else(|| ArgsError::MissingValue(option.to_string()))
+}
+
+fn invalid(option: &str, value: String) -> ArgsError {
+    ArgsError::InvalidValue {
+        option: option.to_string(),
+        value,
+    }
+}
+
+/// Parses a size like "200x100", neither side may be 0
+fn parse_size(size: &str) -> Option<(usize, usize)> {
+    let (width, height) = size.split_once(['x', 'X'])?;
+    let width = width.trim().parse().ok()?;
+    let height = height.trim().parse().ok()?;
+    (width > 0 && height > 0).then_some((width, height))
+}
+
+/// Klein bottles twist the top and bottom edges like Golly does by default, `klein-bottle-y`
+/// twists the left and right edges instead
+fn parse_topology(name: &str) -> Option<Topology> {
+    match name.to_ascii_lowercase().as_str() {
+        "torus" => Some(Topology::Torus),
+        "plane" => Some(Topology::Plane),
+        "vertical-cylinder" => Some(Topology::VerticalCylinder),
+        "horizontal-cylinder" => Some(Topology::HorizontalCylinder),
+        "klein-bottle" => Some(Topology::KleinBottle { twisted_x: true }),
+        "klein-bottle-y" => Some(Topology::KleinBottle { twisted_x: false }),
+        "cross-surface" => Some(Topology::CrossSurface),
+        _ => None,
+    }
+}
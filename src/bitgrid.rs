@@ -0,0 +1,304 @@
+use crate::executor::Executor;
+use crate::rule::{LifeLike, Neighbors};
+use crate::topology::Topology;
+
+/// A fixed size grid of two state cells packed into bits, 64 cells per word. Row `y` is stored
+/// in `words` words starting at `y * words`, cell `x` is bit `x % 64` of word `x / 64`. Bits past
+/// the width are always 0.
+///
+/// Stepping computes the neighbor counts of 64 cells at once with bitwise adders, so any
+/// outer-totalistic rule runs at the same speed.
+#[derive(Clone)]
+pub struct BitGrid {
+    width: usize,
+    height: usize,
+    words: usize,
+    rows: Vec<u64>,
+    /// The rows of the next generation, kept around to avoid allocating every step
+    next: Vec<u64>,
+}
+
+impl BitGrid {
+    pub fn new(width: usize, height: usize) -> Self {
+        let words = width.div_ceil(64);
+        Self {
+            width,
+            height,
+            words,
+            rows: vec![0; words * height],
+            next: vec![0; words * height],
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> bool {
+        self.rows[y * self.words + x / 64] & 1 << (x % 64) != 0
+    }
+
+    pub fn set(&mut self, x: usize, y: usize, alive: bool) {
+        let word = &mut self.rows[y * self.words + x / 64];
+        if alive {
+            *word |= 1 << (x % 64);
+        } else {
+            *word &= !(1 << (x % 64));
+        }
+    }
+
+    pub fn population(&self) -> u64 {
+        self.rows.iter().map(|word| word.count_ones() as u64).sum()
+    }
+
+    /// The coordinates of the live cells, row by row
+    pub fn live_cells(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.rows.iter().enumerate().flat_map(move |(index, word)| {
+            let (y, x0) = (index / self.words, index % self.words * 64);
+            let mut word = *word;
+            std::iter::from_fn(move || {
+                if word == 0 {
+                    return None;
+                }
+                let bit = word.trailing_zeros() as usize;
+                word &= word - 1;
+                Some((x0 + bit, y))
+            })
+        })
+    }
+
+    /// The bits of the last word of a row that are cells
+    fn last_word_mask(&self) -> u64 {
+        match self.width % 64 {
+            0 => !0,
+            bits => (1 << bits) - 1,
+        }
+    }
+
+    /// Whether the cell at a position next to the grid is alive, following the topology
+    fn wrapped(&self, x: i64, y: i64, topology: Topology) -> bool {
+        topology
+            .wrap(x, y, self.width, self.height)
+            .is_some_and(|(x, y)| self.get(x, y))
+    }
+
+    /// The row past the bottom or top edge at `y`, following the topology
+    fn ghost_row(&self, y: i64, topology: Topology) -> Vec<u64> {
+        let mut words = vec![0; self.words];
+        for x in 0..self.width {
+            if self.wrapped(x as i64, y, topology) {
+                words[x / 64] |= 1 << (x % 64);
+            }
+        }
+        words
+    }
+
+    /// Advances the grid by one generation of a two state rule
+    pub fn step(&mut self, rule: &LifeLike, topology: Topology) {
+        if self.width == 0 || self.height == 0 {
+            return;
+        }
+        let mut next = std::mem::take(&mut self.next);
+        Generation::new(self, rule, topology).step_rows(0, &mut next);
+        self.next = std::mem::replace(&mut self.rows, next);
+    }
+
+    /// Like `step`, but evolves horizontal bands of rows in parallel. Every band reads the rows
+    /// around it from the current generation, so the result is the same as stepping serially.
+    pub fn step_parallel(&mut self, rule: &LifeLike, topology: Topology, executor: &dyn Executor) {
+        let bands = executor.threads().min(self.height / MIN_BAND_ROWS);
+        if bands <= 1 || self.width == 0 {
+            return self.step(rule, topology);
+        }
+        let band_rows = self.height.div_ceil(bands);
+        let mut next = std::mem::take(&mut self.next);
+        let generation = Generation::new(self, rule, topology);
+        let generation = &generation;
+        let tasks = next
+            .chunks_mut(band_rows * self.words)
+            .enumerate()
+            .map(|(band, next)| {
+                Box::new(move || generation.step_rows(band * band_rows, next))
+                    as Box<dyn FnOnce() + Send>
+            })
+            .collect();
+        executor.run(tasks);
+        self.next = std::mem::replace(&mut self.rows, next);
+    }
+}
+
+/// Bands are at least this many rows high, smaller ones aren't worth a task
+const MIN_BAND_ROWS: usize = 16;
+
+/// Everything needed to compute the next generation of any rows of a grid: the current rows and
+/// the cells around the grid as the topology joins its edges
+struct Generation<'a> {
+    rows: &'a [u64],
+    words: usize,
+    height: i64,
+    /// The rows past the bottom and top edge
+    below: Vec<u64>,
+    above: Vec<u64>,
+    /// The cells left and right of every row, from the row below the grid to the one above it
+    sides: Vec<(bool, bool)>,
+    rule: PackedRule,
+    last_mask: u64,
+    last_bit: usize,
+}
+
+impl<'a> Generation<'a> {
+    fn new(grid: &'a BitGrid, rule: &LifeLike, topology: Topology) -> Self {
+        let (width, height) = (grid.width as i64, grid.height as i64);
+        Self {
+            rows: &grid.rows,
+            words: grid.words,
+            height,
+            below: grid.ghost_row(-1, topology),
+            above: grid.ghost_row(height, topology),
+            sides: (-1..=height)
+                .map(|y| {
+                    (
+                        grid.wrapped(-1, y, topology),
+                        grid.wrapped(width, y, topology),
+                    )
+                })
+                .collect(),
+            rule: PackedRule::new(rule),
+            last_mask: grid.last_word_mask(),
+            last_bit: (grid.width - 1) % 64,
+        }
+    }
+
+    fn row(&self, y: i64) -> &[u64] {
+        match y {
+            -1 => &self.below,
+            y if y == self.height => &self.above,
+            y => &self.rows[y as usize * self.words..(y as usize + 1) * self.words],
+        }
+    }
+
+    /// Writes the next generation of the rows starting at `first_row` to `next`
+    fn step_rows(&self, first_row: usize, next: &mut [u64]) {
+        for (y, next) in (first_row..).zip(next.chunks_exact_mut(self.words)) {
+            let rows = [
+                self.row(y as i64 - 1),
+                self.row(y as i64),
+                self.row(y as i64 + 1),
+            ];
+            let sides = [self.sides[y], self.sides[y + 1], self.sides[y + 2]];
+            for (i, next_word) in next.iter_mut().enumerate() {
+                // [west, center, east] of the rows below, at and above the cells
+                let neighbors = [
+                    shifted(rows[0], i, sides[0], self.last_bit),
+                    shifted(rows[1], i, sides[1], self.last_bit),
+                    shifted(rows[2], i, sides[2], self.last_bit),
+                ];
+                *next_word = self.rule.apply(neighbors);
+            }
+            next[self.words - 1] &= self.last_mask;
+        }
+    }
+}
+
+/// The cells west of, at and east of the 64 cells of word `i` of a row, given whether the cells
+/// left and right of the row are alive and the bit of the last cell in the last word
+fn shifted(row: &[u64], i: usize, (left, right): (bool, bool), last_bit: usize) -> [u64; 3] {
+    let west = row[i] << 1
+        | if i == 0 {
+            left as u64
+        } else {
+            row[i - 1] >> 63
+        };
+    let east = row[i] >> 1
+        | match row.get(i + 1) {
+            Some(next) => next << 63,
+            // Bits past the width are 0, so the cell right of the row only needs to be added
+            None => (right as u64) << last_bit,
+        };
+    [west, row[i], east]
+}
+
+/// A Life-like rule prepared for stepping 64 cells at once
+struct PackedRule {
+    /// Masks selecting which of the 3x3 cells are neighbors, for the rows below, at and above the
+    /// cell from west to east
+    inputs: [[u64; 3]; 3],
+    /// The counts that lead to a live cell, each as the bits that differ from the count in binary
+    /// when it matches, and a mask and flip turning the state of the cell into whether it can
+    /// become alive
+    outcomes: Vec<([u64; 4], u64, u64)>,
+}
+
+/// Adds three bits of 64 numbers at once, returning the sum and carry bits
+fn full_add(a: u64, b: u64, c: u64) -> (u64, u64) {
+    let partial = a ^ b;
+    (partial ^ c, a & b | partial & c)
+}
+
+impl PackedRule {
+    fn new(rule: &LifeLike) -> Self {
+        let inputs = match rule.neighbors {
+            Neighbors::Moore => [[true, true, true], [true, false, true], [true, true, true]],
+            // Without the north-east and south-west cells
+            Neighbors::Hexagonal => [
+                [false, true, true],
+                [true, false, true],
+                [true, true, false],
+            ],
+            Neighbors::VonNeumann => [
+                [false, true, false],
+                [true, false, true],
+                [false, true, false],
+            ],
+        };
+        let outcomes = (0..9)
+            .filter_map(|count| {
+                let (mask, flip) = match (rule.birth[count], rule.survival[count]) {
+                    (true, true) => (0, !0),
+                    (true, false) => (!0, !0),
+                    (false, true) => (!0, 0),
+                    (false, false) => return None,
+                };
+                let bits = [0, 1, 2, 3].map(|b| if count >> b & 1 == 1 { 0 } else { !0 });
+                Some((bits, mask, flip))
+            })
+            .collect();
+        Self {
+            inputs: inputs.map(|row| row.map(|input| if input { !0 } else { 0 })),
+            outcomes,
+        }
+    }
+
+    /// The next states of 64 cells from the cells around them
+    fn apply(&self, cells: [[u64; 3]; 3]) -> u64 {
+        // Every row adds up to at most 3 neighbors, in two bits
+        let [(ones_a, twos_a), (ones_b, twos_b), (ones_c, twos_c)] = [0, 1, 2].map(|row| {
+            let [west, center, east] = cells[row];
+            let [west_input, center_input, east_input] = self.inputs[row];
+            full_add(west & west_input, center & center_input, east & east_input)
+        });
+        // The neighbor counts in binary, bit `b` of every count in `count[b]`
+        let (ones, carry) = full_add(ones_a, ones_b, ones_c);
+        let (twos_sum, twos_carry) = full_add(twos_a, twos_b, twos_c);
+        let count = [
+            ones,
+            twos_sum ^ carry,
+            twos_carry ^ twos_sum & carry,
+            twos_carry & twos_sum & carry,
+        ];
+        let alive = cells[1][1];
+        let mut next = 0;
+        for (bits, mask, flip) in &self.outcomes {
+            let matches = (count[0] ^ bits[0])
+                & (count[1] ^ bits[1])
+                & (count[2] ^ bits[2])
+                & (count[3] ^ bits[3]);
+            next |= matches & (alive & mask ^ flip);
+        }
+        next
+    }
+}
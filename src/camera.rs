@@ -0,0 +1,290 @@
+use bevy::input::mouse::{MouseMotion, MouseScrollUnit, MouseWheel};
+use bevy::prelude::*;
+use game_of_life::state::{BoundingBox, Coord, GridSize, StateGrid};
+use game_of_life::Universe;
+
+use crate::controls::{Action, Controls};
+use crate::grid::{cell_corner, cell_size, row_offset, Position, ViewOrigin, VisibleCells};
+
+/// How much one notch of the mouse wheel zooms in or out
+const ZOOM_FACTOR: f32 = 1.2;
+/// Touchpads scroll in pixels, this many of them count as one notch
+const PIXELS_PER_NOTCH: f32 = 50.0;
+/// The smallest and largest projection scale. At 1 the board just fits in the window.
+const MIN_SCALE: f32 = 0.01;
+const MAX_SCALE: f32 = 4.0;
+/// How fast the pan keys move the view, in screen pixels per second
+const PAN_SPEED: f32 = 400.0;
+/// Room left around a framed pattern
+const FIT_MARGIN: f32 = 1.2;
+/// Unbounded grids draw at most this many columns and rows around the camera, so zooming out on a
+/// large board doesn't make a texture larger than the GPU allows
+const MAX_VISIBLE_SIDE: i64 = 2048;
+
+/// Owns the 2d camera: zooming with the mouse wheel, panning with a mouse drag or the pan keys and
+/// framing the live cells
+pub struct CameraPlugin;
+
+impl Plugin for CameraPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(HoveredCell(None))
+            .add_startup_system(setup_camera)
+            .add_system_to_stage(CoreStage::PreUpdate, update_hovered_cell)
+            .add_system(zoom_camera.label("camera"))
+            .add_system(pan_camera.label("camera"))
+            .add_system(fit_pattern.label("camera"))
+            .add_system(update_visible_cells.label("visible_cells").after("camera"));
+    }
+}
+
+#[derive(Component)]
+struct MainCamera;
+
+/// The board cell under the cursor, if the cursor is over the board
+pub struct HoveredCell(pub Option<Position>);
+
+fn setup_camera(mut commands: Commands) {
+    commands
+        .spawn_bundle(OrthographicCameraBundle::new_2d())
+        .insert(MainCamera);
+}
+
+fn window_size(window: &Window) -> Vec2 {
+    Vec2::new(window.width(), window.height())
+}
+
+/// Converts the cursor position to world coordinates through the camera transform
+fn cursor_to_world(
+    window: &Window,
+    transform: &Transform,
+    projection: &OrthographicProjection,
+) -> Option<Vec2> {
+    let cursor = window.cursor_position()?;
+    let offset = cursor - window_size(window) / 2.0;
+    Some(transform.translation.truncate() + offset * projection.scale)
+}
+
+/// The cell under the cursor is only hovered while it is drawn
+fn update_hovered_cell(
+    windows: Res<Windows>,
+    grid_size: Res<GridSize>,
+    universe: Res<Universe>,
+    view_origin: Res<ViewOrigin>,
+    visible: Res<VisibleCells>,
+    cameras: Query<(&Transform, &OrthographicProjection), With<MainCamera>>,
+    mut hovered: ResMut<HoveredCell>,
+) {
+    let window = windows.get_primary().unwrap();
+    let (transform, projection) = cameras.single();
+    let cell = cursor_to_world(window, transform, projection).and_then(|world| {
+        let cell = world / cell_size(window, *grid_size);
+        let y = (cell.y + grid_size.height as f32 / 2.0).floor();
+        // Hexagonal rows are shifted, so the row tells which column the cursor is in
+        let offset = row_offset(y as i32, grid_size.height, universe.rule().is_hexagonal());
+        let x = (cell.x + grid_size.width as f32 / 2.0 - offset).floor();
+        let origin = view_origin.0;
+        let drawn = visible.0.contains(Coord::new(
+            origin.x.saturating_add(x as i64),
+            origin.y.saturating_add(y as i64),
+        ));
+        drawn.then_some(Position {
+            x: x as i32,
+            y: y as i32,
+        })
+    });
+    if hovered.0 != cell {
+        hovered.0 = cell;
+    }
+}
+
+/// Zooms around the cursor, so the cell under it stays in place
+fn zoom_camera(
+    mut wheel_events: EventReader<MouseWheel>,
+    windows: Res<Windows>,
+    mut cameras: Query<(&mut Transform, &mut OrthographicProjection), With<MainCamera>>,
+) {
+    let notches: f32 = wheel_events
+        .iter()
+        .map(|event| match event.unit {
+            MouseScrollUnit::Line => event.y,
+            MouseScrollUnit::Pixel => event.y / PIXELS_PER_NOTCH,
+        })
+        .sum();
+    if notches == 0.0 {
+        return;
+    }
+    let window = windows.get_primary().unwrap();
+    let (mut transform, mut projection) = cameras.single_mut();
+    let old_scale = projection.scale;
+    let new_scale = (old_scale * ZOOM_FACTOR.powf(-notches)).clamp(MIN_SCALE, MAX_SCALE);
+    if let Some(cursor) = window.cursor_position() {
+        let offset = cursor - window_size(window) / 2.0;
+        transform.translation += (offset * (old_scale - new_scale)).extend(0.0);
+    }
+    projection.scale = new_scale;
+}
+
+fn pan_camera(
+    controls: Controls,
+    mut motion_events: EventReader<MouseMotion>,
+    time: Res<Time>,
+    mut cameras: Query<(&mut Transform, &OrthographicProjection), With<MainCamera>>,
+) {
+    let mut delta = Vec2::ZERO;
+    for event in motion_events.iter() {
+        if controls.pressed(Action::Pan) {
+            // Screen coordinates point down, world coordinates point up
+            delta += Vec2::new(-event.delta.x, event.delta.y);
+        }
+    }
+    let mut direction = Vec2::ZERO;
+    if controls.pressed(Action::PanUp) {
+        direction.y += 1.0;
+    }
+    if controls.pressed(Action::PanDown) {
+        direction.y -= 1.0;
+    }
+    if controls.pressed(Action::PanLeft) {
+        direction.x -= 1.0;
+    }
+    if controls.pressed(Action::PanRight) {
+        direction.x += 1.0;
+    }
+    delta += direction * PAN_SPEED * time.delta_seconds();
+    if delta == Vec2::ZERO {
+        return;
+    }
+    let (mut transform, projection) = cameras.single_mut();
+    transform.translation += (delta * projection.scale).extend(0.0);
+}
+
+/// Frames the bounding box of the live cells. Unbounded grids first move the view origin to the
+/// pattern; patterns too large to frame at the largest zoom are centered.
+fn fit_pattern(
+    controls: Controls,
+    windows: Res<Windows>,
+    grid_size: Res<GridSize>,
+    universe: Res<Universe>,
+    mut view_origin: ResMut<ViewOrigin>,
+    mut cameras: Query<(&mut Transform, &mut OrthographicProjection), With<MainCamera>>,
+) {
+    if !controls.just_released(Action::FrameCells) {
+        return;
+    }
+    let (mut transform, mut projection) = cameras.single_mut();
+    let bounds = match universe.bounding_box() {
+        Some(bounds) => bounds,
+        None => {
+            transform.translation.x = 0.0;
+            transform.translation.y = 0.0;
+            projection.scale = 1.0;
+            return;
+        }
+    };
+    let width = grid_size.width as i64;
+    let height = grid_size.height as i64;
+    if !matches!(universe.grid(), StateGrid::Bounded(_)) {
+        let center = Coord::new(
+            bounds.min.x.saturating_add((bounds.width() / 2) as i64),
+            bounds.min.y.saturating_add((bounds.height() / 2) as i64),
+        );
+        view_origin.0 = Coord::new(center.x - width / 2, center.y - height / 2);
+    }
+    let origin = view_origin.0;
+    let window = windows.get_primary().unwrap();
+    let cell_size = cell_size(window, *grid_size);
+    // Board positions are i32, far away cells are out of view at any zoom anyway
+    let relative = |x: i64, origin: i64| {
+        x.saturating_sub(origin)
+            .clamp(i32::MIN.into(), i32::MAX.into())
+    };
+    let (min_x, max_x) = (
+        relative(bounds.min.x, origin.x),
+        relative(bounds.max.x, origin.x),
+    );
+    let (min_y, max_y) = (
+        relative(bounds.min.y, origin.y),
+        relative(bounds.max.y, origin.y),
+    );
+    // Hexagonal rows are shifted, the bottom and top rows are shifted the most
+    let hexagonal = universe.rule().is_hexagonal();
+    let offsets = [min_y, max_y].map(|y| row_offset(y as i32, grid_size.height, hexagonal));
+    let bottom_left = cell_corner(
+        min_x as f32 + offsets[0].min(offsets[1]),
+        min_y as f32,
+        *grid_size,
+        cell_size,
+    );
+    let top_right = cell_corner(
+        (max_x + 1) as f32 + offsets[0].max(offsets[1]),
+        (max_y + 1) as f32,
+        *grid_size,
+        cell_size,
+    );
+    let (left, bottom) = (bottom_left.x, bottom_left.y);
+    let (right, top) = (top_right.x, top_right.y);
+    let window = window_size(window);
+    transform.translation.x = (left + right) / 2.0;
+    transform.translation.y = (bottom + top) / 2.0;
+    let scale = ((right - left) / window.x).max((top - bottom) / window.y) * FIT_MARGIN;
+    projection.scale = scale.clamp(MIN_SCALE, MAX_SCALE);
+}
+
+/// Follows the camera with the cells that are drawn. Hexagonal rows are shifted, so enough
+/// columns are drawn to fill the window in the bottom and the top row.
+fn update_visible_cells(
+    windows: Res<Windows>,
+    grid_size: Res<GridSize>,
+    universe: Res<Universe>,
+    view_origin: Res<ViewOrigin>,
+    cameras: Query<(&Transform, &OrthographicProjection), With<MainCamera>>,
+    mut visible: ResMut<VisibleCells>,
+) {
+    let cells = if matches!(universe.grid(), StateGrid::Bounded(_)) {
+        VisibleCells::board(*view_origin, *grid_size)
+    } else {
+        let window = windows.get_primary().unwrap();
+        let (transform, projection) = cameras.single();
+        let cell_size = cell_size(window, *grid_size);
+        if cell_size <= 0.0 {
+            // Minimized windows show no cells, the last ones stay until the window is restored
+            return;
+        }
+        let center = transform.translation.truncate();
+        let half_window = window_size(window) / 2.0 * projection.scale;
+        let board_center = Vec2::new(grid_size.width as f32, grid_size.height as f32) / 2.0;
+        let bottom_left = (center - half_window) / cell_size + board_center;
+        let top_right = (center + half_window) / cell_size + board_center;
+        let (min_y, max_y) = (bottom_left.y.floor(), top_right.y.floor());
+        let hexagonal = universe.rule().is_hexagonal();
+        let offsets = [min_y, max_y].map(|y| row_offset(y as i32, grid_size.height, hexagonal));
+        let min_x = (bottom_left.x - offsets[0].max(offsets[1])).floor();
+        let max_x = (top_right.x - offsets[0].min(offsets[1])).floor();
+        // Around the camera, the drawn cells are cut down to the largest size on each side
+        let clamp = |min: f32, max: f32, center: f32| {
+            let center = (center.floor() as i64).clamp(min as i64, max as i64);
+            let min = (min as i64).max(center.saturating_sub(MAX_VISIBLE_SIDE / 2));
+            (
+                min,
+                (max as i64).min(min.saturating_add(MAX_VISIBLE_SIDE - 1)),
+            )
+        };
+        let cells_center = center / cell_size + board_center;
+        let (min_x, max_x) = clamp(min_x, max_x, cells_center.x);
+        let (min_y, max_y) = clamp(min_y, max_y, cells_center.y);
+        let origin = view_origin.0;
+        VisibleCells(BoundingBox {
+            min: Coord::new(
+                origin.x.saturating_add(min_x),
+                origin.y.saturating_add(min_y),
+            ),
+            max: Coord::new(
+                origin.x.saturating_add(max_x),
+                origin.y.saturating_add(max_y),
+            ),
+        })
+    };
+    if *visible != cells {
+        *visible = cells;
+    }
+}
@@ -0,0 +1,254 @@
+use std::collections::BTreeMap;
+use std::env;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use bevy::prelude::*;
+use game_of_life::rule::Rule;
+use game_of_life::state::GridSize;
+use game_of_life::undo::DEFAULT_MEMORY_MIB;
+use game_of_life::Universe;
+use serde::de::IgnoredAny;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::controls::Bindings;
+use crate::files::{find_rule, pattern_dir};
+use crate::grid::CellColors;
+use crate::Speed;
+
+/// Writes the settings changed in the app back to the config file
+pub struct ConfigPlugin;
+
+impl Plugin for ConfigPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_to_stage(CoreStage::PostUpdate, save_config);
+    }
+}
+
+/// The settings kept between runs, read from `config.toml` in the `game_of_life` directory of the
+/// XDG config directory. Missing keys keep their defaults.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// A rulestring or the name of a .rule file next to the config file or in `rules/`. `load`
+    /// reads it, since finding the file needs the path of the config.
+    #[serde(serialize_with = "serialize_rule", deserialize_with = "skip_rule")]
+    pub rule: Rule,
+    /// Seconds between updates
+    pub speed: f32,
+    /// The memory in MiB undo may use, 0 turns it off
+    pub history_memory: usize,
+    /// The pattern opened last, which Ctrl+O and Ctrl+S use unless the command line names one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pattern: Option<PathBuf>,
+    pub grid: GridConfig,
+    pub colors: ColorConfig,
+    /// Key bindings, from the name of an action to a list of keys like "Ctrl+Y, Ctrl+Shift+Z"
+    pub keys: BTreeMap<String, String>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            rule: Rule::default(),
+            speed: 0.1,
+            history_memory: DEFAULT_MEMORY_MIB,
+            pattern: None,
+            grid: GridSize::default().into(),
+            colors: ColorConfig::default(),
+            keys: BTreeMap::new(),
+        }
+    }
+}
+
+/// The size of the bounded grid
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct GridConfig {
+    pub width: usize,
+    pub height: usize,
+}
+
+impl From<GridSize> for GridConfig {
+    fn from(size: GridSize) -> Self {
+        Self {
+            width: size.width,
+            height: size.height,
+        }
+    }
+}
+
+impl From<GridConfig> for GridSize {
+    fn from(grid: GridConfig) -> Self {
+        Self {
+            width: grid.width,
+            height: grid.height,
+        }
+    }
+}
+
+/// The colors of the cells as [r, g, b]. Rule tables may pick their own colors.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct ColorConfig {
+    pub dead: [u8; 3],
+    pub alive: [u8; 3],
+    /// Dying cells of Generations rules fade from the first color to the second one
+    pub dying: [[u8; 3]; 2],
+}
+
+impl Default for ColorConfig {
+    fn default() -> Self {
+        Self {
+            dead: [0, 0, 0],
+            alive: [255, 255, 255],
+            dying: [[255, 166, 26], [77, 0, 26]],
+        }
+    }
+}
+
+impl From<ColorConfig> for CellColors {
+    fn from(colors: ColorConfig) -> Self {
+        let color = |[r, g, b]: [u8; 3]| Color::rgb_u8(r, g, b);
+        Self {
+            dead: color(colors.dead),
+            alive: color(colors.alive),
+            dying: colors.dying.map(color),
+        }
+    }
+}
+
+fn serialize_rule<S: Serializer>(rule: &Rule, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&rule.to_string())
+}
+
+fn skip_rule<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Rule, D::Error> {
+    IgnoredAny::deserialize(deserializer)?;
+    Ok(Rule::default())
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Read(io::Error),
+    Parse(toml::de::Error),
+    Invalid(String),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Read(err) => write!(f, "could not be read: {}", err),
+            Self::Parse(err) => write!(f, "is malformed: {}", err),
+            Self::Invalid(reason) => write!(f, "is invalid: {}", reason),
+        }
+    }
+}
+
+impl Error for ConfigError {}
+
+impl Config {
+    /// Where the config file is, if there is a config directory
+    pub fn path() -> Option<PathBuf> {
+        let config_dir = match env::var_os("XDG_CONFIG_HOME").filter(|dir| !dir.is_empty()) {
+            Some(dir) => PathBuf::from(dir),
+            None => PathBuf::from(env::var_os("HOME")?).join(".config"),
+        };
+        Some(config_dir.join("game_of_life").join("config.toml"))
+    }
+
+    /// Reads the config file, or returns the defaults if there is none
+    pub fn load(path: &Path) -> Result<Self, ConfigError> {
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(err) => return Err(ConfigError::Read(err)),
+        };
+        let value: toml::Value = toml::from_str(&text).map_err(ConfigError::Parse)?;
+        let rule = value.get("rule").cloned();
+        let mut config: Self = value.try_into().map_err(ConfigError::Parse)?;
+        if let Some(rule) = rule {
+            let name = rule.as_str().ok_or_else(|| {
+                ConfigError::Invalid(format!("rule {} is not a rulestring or a name", rule))
+            })?;
+            config.rule = find_rule(name.trim(), pattern_dir(path))
+                .map_err(|err| ConfigError::Invalid(format!("rule '{}': {}", name, err)))?;
+        }
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Checks the values the types of the fields allow but the app doesn't
+    fn validate(&self) -> Result<(), ConfigError> {
+        if !self.speed.is_finite() || self.speed < 0.0 {
+            return Err(ConfigError::Invalid(format!(
+                "speed {} is not a number of seconds",
+                self.speed
+            )));
+        }
+        if self.grid.width == 0 || self.grid.height == 0 {
+            return Err(ConfigError::Invalid(format!(
+                "the grid can't be {}x{} cells",
+                self.grid.width, self.grid.height
+            )));
+        }
+        Bindings::new(&self.keys).map_err(ConfigError::Invalid)?;
+        Ok(())
+    }
+
+    /// Writes the config file, creating its directory if needed
+    pub fn save(&self, path: &Path) -> Result<(), Box<dyn Error>> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, toml::to_string(self)?)?;
+        Ok(())
+    }
+}
+
+/// The config file and the settings it holds
+pub struct ConfigFile {
+    pub path: Option<PathBuf>,
+    pub saved: Config,
+}
+
+/// Keeps the config up to date with changes of the speed, grid size and rule in the app, and
+/// writes it to the file whenever it changes. Opening a pattern updates the config as well.
+/// Settings from the command line are only saved once they are changed.
+fn save_config(
+    speed: Res<Speed>,
+    grid_size: Res<GridSize>,
+    universe: Res<Universe>,
+    mut last_rule: Local<Option<String>>,
+    mut config: ResMut<Config>,
+    mut file: ResMut<ConfigFile>,
+) {
+    if speed.is_changed() && !speed.is_added() && config.speed != speed.0 {
+        config.speed = speed.0;
+    }
+    if grid_size.is_changed() && !grid_size.is_added() && config.grid != (*grid_size).into() {
+        config.grid = (*grid_size).into();
+    }
+    // The universe changes every generation, comparing rulestrings is cheaper than comparing
+    // whole rule tables
+    if universe.is_changed() {
+        let rulestring = universe.rule().to_string();
+        if last_rule.as_ref() != Some(&rulestring) {
+            if last_rule.is_some() && config.rule.to_string() != rulestring {
+                config.rule = universe.rule().clone();
+            }
+            *last_rule = Some(rulestring);
+        }
+    }
+    if !config.is_changed() || *config == file.saved {
+        return;
+    }
+    file.saved = config.clone();
+    if let Some(path) = &file.path {
+        if let Err(err) = config.save(path) {
+            error!("Could not save the config to {}: {}", path.display(), err);
+        }
+    }
+}
@@ -0,0 +1,558 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::marker::PhantomData;
+use std::str::FromStr;
+
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+
+/// Maps the keys and mouse buttons to actions and shows the bindings in a help overlay
+pub struct ControlsPlugin;
+
+impl Plugin for ControlsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Bindings>().add_system(toggle_help);
+    }
+}
+
+/// What the keyboard and mouse can do
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Action {
+    TogglePause,
+    SlowDown,
+    SpeedUp,
+    Step,
+    StepNumber,
+    RunToNumber,
+    DiscardNumber,
+    MoreGenerations,
+    FewerGenerations,
+    Clear,
+    Draw,
+    Erase,
+    PreviousState,
+    NextState,
+    Pan,
+    PanUp,
+    PanDown,
+    PanLeft,
+    PanRight,
+    FrameCells,
+    GrowGrid,
+    ShrinkGrid,
+    NextEngine,
+    NextTopology,
+    ToggleRenderMode,
+    Open,
+    Save,
+    Undo,
+    Redo,
+    ToggleHelp,
+}
+
+/// Every action with its name in the config file, its default bindings and what it does, in the
+/// order the help lists them
+const ACTIONS: [(Action, &str, &str, &str); 30] = [
+    (
+        Action::TogglePause,
+        "TogglePause",
+        "Space",
+        "Pause or resume",
+    ),
+    (
+        Action::SlowDown,
+        "SlowDown",
+        "Left",
+        "Wait longer between updates",
+    ),
+    (
+        Action::SpeedUp,
+        "SpeedUp",
+        "Right",
+        "Wait less between updates",
+    ),
+    (Action::Step, "Step", "Tab", "Step one generation"),
+    (
+        Action::StepNumber,
+        "StepNumber",
+        "N",
+        "Step the typed number of generations",
+    ),
+    (
+        Action::RunToNumber,
+        "RunToNumber",
+        "G",
+        "Run until the typed generation",
+    ),
+    (
+        Action::DiscardNumber,
+        "DiscardNumber",
+        "Back",
+        "Discard the typed number",
+    ),
+    (
+        Action::MoreGenerations,
+        "MoreGenerations",
+        "PageUp",
+        "Double the generations per update",
+    ),
+    (
+        Action::FewerGenerations,
+        "FewerGenerations",
+        "PageDown",
+        "Halve the generations per update",
+    ),
+    (Action::Clear, "Clear", "C", "Clear the board"),
+    (
+        Action::Draw,
+        "Draw",
+        "MouseLeft",
+        "Draw cells, or kill drawn ones",
+    ),
+    (Action::Erase, "Erase", "MouseRight", "Kill cells"),
+    (
+        Action::PreviousState,
+        "PreviousState",
+        "[",
+        "Draw the previous state",
+    ),
+    (Action::NextState, "NextState", "]", "Draw the next state"),
+    (Action::Pan, "Pan", "MouseMiddle", "Drag the view"),
+    (Action::PanUp, "PanUp", "W", "Move the view up"),
+    (Action::PanDown, "PanDown", "S", "Move the view down"),
+    (Action::PanLeft, "PanLeft", "A", "Move the view left"),
+    (Action::PanRight, "PanRight", "D", "Move the view right"),
+    (
+        Action::FrameCells,
+        "FrameCells",
+        "F",
+        "Frame the live cells",
+    ),
+    (Action::GrowGrid, "GrowGrid", "Up", "Grow the grid"),
+    (Action::ShrinkGrid, "ShrinkGrid", "Down", "Shrink the grid"),
+    (
+        Action::NextEngine,
+        "NextEngine",
+        "U",
+        "Switch to the next engine",
+    ),
+    (
+        Action::NextTopology,
+        "NextTopology",
+        "T",
+        "Switch to the next topology",
+    ),
+    (
+        Action::ToggleRenderMode,
+        "ToggleRenderMode",
+        "R",
+        "Switch between sprites and a texture",
+    ),
+    (Action::Open, "Open", "Ctrl+O", "Load the pattern file"),
+    (Action::Save, "Save", "Ctrl+S", "Save the pattern file"),
+    (Action::Undo, "Undo", "Ctrl+Z", "Undo"),
+    (Action::Redo, "Redo", "Ctrl+Y, Ctrl+Shift+Z", "Redo"),
+    (
+        Action::ToggleHelp,
+        "ToggleHelp",
+        "H",
+        "Show or hide this help",
+    ),
+];
+
+/// The names of the keys bindings can use, besides letters and digits
+const KEY_NAMES: [(KeyCode, &str); 45] = [
+    (KeyCode::Space, "Space"),
+    (KeyCode::Tab, "Tab"),
+    (KeyCode::Back, "Back"),
+    (KeyCode::Return, "Enter"),
+    (KeyCode::Escape, "Escape"),
+    (KeyCode::Insert, "Insert"),
+    (KeyCode::Delete, "Delete"),
+    (KeyCode::Home, "Home"),
+    (KeyCode::End, "End"),
+    (KeyCode::PageUp, "PageUp"),
+    (KeyCode::PageDown, "PageDown"),
+    (KeyCode::Up, "Up"),
+    (KeyCode::Down, "Down"),
+    (KeyCode::Left, "Left"),
+    (KeyCode::Right, "Right"),
+    (KeyCode::LBracket, "["),
+    (KeyCode::RBracket, "]"),
+    (KeyCode::Minus, "-"),
+    (KeyCode::Equals, "="),
+    (KeyCode::Comma, "Comma"),
+    (KeyCode::Period, "."),
+    (KeyCode::Slash, "/"),
+    (KeyCode::Backslash, "\\"),
+    (KeyCode::Semicolon, ";"),
+    (KeyCode::Apostrophe, "'"),
+    (KeyCode::Grave, "`"),
+    (KeyCode::F1, "F1"),
+    (KeyCode::F2, "F2"),
+    (KeyCode::F3, "F3"),
+    (KeyCode::F4, "F4"),
+    (KeyCode::F5, "F5"),
+    (KeyCode::F6, "F6"),
+    (KeyCode::F7, "F7"),
+    (KeyCode::F8, "F8"),
+    (KeyCode::F9, "F9"),
+    (KeyCode::F10, "F10"),
+    (KeyCode::F11, "F11"),
+    (KeyCode::F12, "F12"),
+    (KeyCode::NumpadAdd, "NumpadAdd"),
+    (KeyCode::NumpadSubtract, "NumpadSubtract"),
+    (KeyCode::NumpadMultiply, "NumpadMultiply"),
+    (KeyCode::NumpadDivide, "NumpadDivide"),
+    (KeyCode::NumpadEnter, "NumpadEnter"),
+    (KeyCode::NumpadDecimal, "NumpadDecimal"),
+    (KeyCode::Pause, "Pause"),
+];
+
+const LETTERS: [KeyCode; 26] = [
+    KeyCode::A,
+    KeyCode::B,
+    KeyCode::C,
+    KeyCode::D,
+    KeyCode::E,
+    KeyCode::F,
+    KeyCode::G,
+    KeyCode::H,
+    KeyCode::I,
+    KeyCode::J,
+    KeyCode::K,
+    KeyCode::L,
+    KeyCode::M,
+    KeyCode::N,
+    KeyCode::O,
+    KeyCode::P,
+    KeyCode::Q,
+    KeyCode::R,
+    KeyCode::S,
+    KeyCode::T,
+    KeyCode::U,
+    KeyCode::V,
+    KeyCode::W,
+    KeyCode::X,
+    KeyCode::Y,
+    KeyCode::Z,
+];
+
+/// The keys of the digits above the letters and on the numpad
+pub const DIGITS: [(KeyCode, KeyCode); 10] = [
+    (KeyCode::Key0, KeyCode::Numpad0),
+    (KeyCode::Key1, KeyCode::Numpad1),
+    (KeyCode::Key2, KeyCode::Numpad2),
+    (KeyCode::Key3, KeyCode::Numpad3),
+    (KeyCode::Key4, KeyCode::Numpad4),
+    (KeyCode::Key5, KeyCode::Numpad5),
+    (KeyCode::Key6, KeyCode::Numpad6),
+    (KeyCode::Key7, KeyCode::Numpad7),
+    (KeyCode::Key8, KeyCode::Numpad8),
+    (KeyCode::Key9, KeyCode::Numpad9),
+];
+
+const MOUSE_BUTTONS: [(MouseButton, &str); 3] = [
+    (MouseButton::Left, "MouseLeft"),
+    (MouseButton::Right, "MouseRight"),
+    (MouseButton::Middle, "MouseMiddle"),
+];
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Button {
+    Key(KeyCode),
+    Mouse(MouseButton),
+}
+
+impl FromStr for Button {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let letter = match s.as_bytes() {
+            [letter] if letter.is_ascii_alphabetic() => Some(letter.to_ascii_uppercase() - b'A'),
+            _ => None,
+        };
+        let digit = match s.as_bytes() {
+            [digit] if digit.is_ascii_digit() => Some(digit - b'0'),
+            _ => None,
+        };
+        if let Some(letter) = letter {
+            Ok(Self::Key(LETTERS[letter as usize]))
+        } else if let Some(digit) = digit {
+            Ok(Self::Key(DIGITS[digit as usize].0))
+        } else if let Some((key, _)) = KEY_NAMES
+            .iter()
+            .find(|(_, name)| name.eq_ignore_ascii_case(s))
+        {
+            Ok(Self::Key(*key))
+        } else if let Some((button, _)) = MOUSE_BUTTONS
+            .iter()
+            .find(|(_, name)| name.eq_ignore_ascii_case(s))
+        {
+            Ok(Self::Mouse(*button))
+        } else {
+            Err(())
+        }
+    }
+}
+
+impl fmt::Display for Button {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Key(key) => {
+                if let Some(letter) = LETTERS.iter().position(|letter| letter == key) {
+                    write!(f, "{}", (b'A' + letter as u8) as char)
+                } else if let Some(digit) = DIGITS.iter().position(|(digit, _)| digit == key) {
+                    write!(f, "{}", digit)
+                } else if let Some((_, name)) = KEY_NAMES.iter().find(|(named, _)| named == key) {
+                    write!(f, "{}", name)
+                } else {
+                    write!(f, "{:?}", key)
+                }
+            }
+            Self::Mouse(button) => match MOUSE_BUTTONS.iter().find(|(named, _)| named == button) {
+                Some((_, name)) => write!(f, "{}", name),
+                None => write!(f, "{:?}", button),
+            },
+        }
+    }
+}
+
+/// A key or mouse button, pressed while holding exactly the given modifiers, like "Ctrl+Shift+Z"
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Binding {
+    pub ctrl: bool,
+    pub shift: bool,
+    pub alt: bool,
+    pub button: Button,
+}
+
+impl FromStr for Binding {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parts: Vec<&str> = s.split('+').map(str::trim).collect();
+        let (button, modifiers) = parts.split_last().ok_or(())?;
+        let mut binding = Self {
+            ctrl: false,
+            shift: false,
+            alt: false,
+            button: button.parse()?,
+        };
+        for modifier in modifiers {
+            let held = match modifier.to_ascii_lowercase().as_str() {
+                "ctrl" => &mut binding.ctrl,
+                "shift" => &mut binding.shift,
+                "alt" => &mut binding.alt,
+                _ => return Err(()),
+            };
+            *held = true;
+        }
+        Ok(binding)
+    }
+}
+
+impl fmt::Display for Binding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (held, name) in [
+            (self.ctrl, "Ctrl+"),
+            (self.shift, "Shift+"),
+            (self.alt, "Alt+"),
+        ] {
+            if held {
+                write!(f, "{}", name)?;
+            }
+        }
+        write!(f, "{}", self.button)
+    }
+}
+
+/// Parses a comma separated list of bindings like "Ctrl+Y, Ctrl+Shift+Z"
+fn parse_bindings(s: &str) -> Result<Vec<Binding>, ()> {
+    s.split(',')
+        .map(str::trim)
+        .filter(|binding| !binding.is_empty())
+        .map(str::parse)
+        .collect()
+}
+
+/// The bindings of every action, in the order the help lists them
+pub struct Bindings(Vec<(Action, Vec<Binding>)>);
+
+impl Default for Bindings {
+    fn default() -> Self {
+        Self(
+            ACTIONS
+                .iter()
+                .map(|(action, _, keys, _)| (*action, parse_bindings(keys).unwrap()))
+                .collect(),
+        )
+    }
+}
+
+impl Bindings {
+    /// The default bindings with the ones of the config file instead, which maps the names of
+    /// actions to lists of bindings. An empty list unbinds the action. Two actions can't share a
+    /// binding.
+    pub fn new(keys: &BTreeMap<String, String>) -> Result<Self, String> {
+        let mut bindings = Self::default();
+        for (name, keys) in keys {
+            let index = ACTIONS
+                .iter()
+                .position(|(_, action_name, _, _)| action_name == name)
+                .ok_or_else(|| format!("there is no action called '{}'", name))?;
+            bindings.0[index].1 = parse_bindings(keys)
+                .map_err(|()| format!("'{}' is not a list of keys for {}", keys, name))?;
+        }
+        for (index, (_, keys)) in bindings.0.iter().enumerate() {
+            for (other, (_, other_keys)) in bindings.0.iter().enumerate().skip(index + 1) {
+                if let Some(key) = keys.iter().find(|key| other_keys.contains(key)) {
+                    return Err(format!(
+                        "{} is bound to both {} and {}",
+                        key, ACTIONS[index].1, ACTIONS[other].1
+                    ));
+                }
+            }
+        }
+        Ok(bindings)
+    }
+
+    fn get(&self, action: Action) -> &[Binding] {
+        self.0
+            .iter()
+            .find(|(bound, _)| *bound == action)
+            .map_or(&[], |(_, bindings)| bindings)
+    }
+
+    /// One line per action with its bindings and what it does
+    fn help(&self) -> String {
+        let lines: Vec<(String, &str)> = self
+            .0
+            .iter()
+            .zip(ACTIONS)
+            .map(|((_, bindings), (_, _, _, description))| {
+                let keys: Vec<String> = bindings.iter().map(Binding::to_string).collect();
+                (keys.join(", "), description)
+            })
+            .chain([
+                ("0-9".to_string(), "Type a number"),
+                ("Wheel".to_string(), "Zoom"),
+            ])
+            .collect();
+        let width = lines.iter().map(|(keys, _)| keys.len()).max().unwrap_or(0);
+        lines
+            .iter()
+            .map(|(keys, description)| format!("{:width$}  {}", keys, description, width = width))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+/// Whether the actions are triggered by the keys and mouse buttons bound to them
+#[derive(SystemParam)]
+pub struct Controls<'w, 's> {
+    keys: Res<'w, Input<KeyCode>>,
+    buttons: Res<'w, Input<MouseButton>>,
+    bindings: Res<'w, Bindings>,
+    #[system_param(ignore)]
+    marker: PhantomData<&'s ()>,
+}
+
+impl<'w, 's> Controls<'w, 's> {
+    /// Whether exactly the modifiers of the binding are held
+    fn modifiers_match(&self, binding: &Binding) -> bool {
+        binding.ctrl
+            == self
+                .keys
+                .any_pressed([KeyCode::LControl, KeyCode::RControl])
+            && binding.shift == self.keys.any_pressed([KeyCode::LShift, KeyCode::RShift])
+            && binding.alt == self.keys.any_pressed([KeyCode::LAlt, KeyCode::RAlt])
+    }
+
+    fn any(
+        &self,
+        action: Action,
+        key: impl Fn(KeyCode) -> bool,
+        button: impl Fn(MouseButton) -> bool,
+    ) -> bool {
+        self.bindings.get(action).iter().any(|binding| {
+            let triggered = match binding.button {
+                Button::Key(code) => key(code),
+                Button::Mouse(code) => button(code),
+            };
+            triggered && self.modifiers_match(binding)
+        })
+    }
+
+    /// Whether a binding of the action is held down
+    pub fn pressed(&self, action: Action) -> bool {
+        self.any(
+            action,
+            |key| self.keys.pressed(key),
+            |button| self.buttons.pressed(button),
+        )
+    }
+
+    /// Whether a binding of the action was let go of in this frame
+    pub fn just_released(&self, action: Action) -> bool {
+        self.any(
+            action,
+            |key| self.keys.just_released(key),
+            |button| self.buttons.just_released(button),
+        )
+    }
+
+    /// The keys let go of in this frame, for typing
+    pub fn released_keys(&self) -> impl Iterator<Item = &KeyCode> {
+        self.keys.get_just_released()
+    }
+}
+
+#[derive(Component)]
+struct HelpOverlay;
+
+/// Shows or hides the list of bindings in the top right corner of the window
+fn toggle_help(
+    mut commands: Commands,
+    controls: Controls,
+    asset_server: Res<AssetServer>,
+    overlays: Query<Entity, With<HelpOverlay>>,
+) {
+    if !controls.just_released(Action::ToggleHelp) {
+        return;
+    }
+    if let Ok(overlay) = overlays.get_single() {
+        commands.entity(overlay).despawn_recursive();
+        return;
+    }
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    top: Val::Px(5.0),
+                    right: Val::Px(5.0),
+                    ..default()
+                },
+                padding: Rect::all(Val::Px(4.0)),
+                ..default()
+            },
+            color: UiColor(Color::rgba(0.0, 0.0, 0.0, 0.8)),
+            ..default()
+        })
+        .insert(HelpOverlay)
+        .with_children(|parent| {
+            parent.spawn_bundle(TextBundle {
+                text: Text::with_section(
+                    controls.bindings.help(),
+                    TextStyle {
+                        font: asset_server.load("fonts/FiraMono-Medium.ttf"),
+                        font_size: 12.0,
+                        color: Color::rgb(1.0, 0.8, 0.2),
+                    },
+                    default(),
+                ),
+                ..default()
+            });
+        });
+}
@@ -0,0 +1,52 @@
+use std::num::NonZeroUsize;
+use std::thread;
+
+/// Runs the bands of a parallel step, so the simulation can use the threads of whatever drives
+/// it. The Bevy app runs them on its compute task pool.
+pub trait Executor: Send + Sync {
+    /// How many tasks can run at once
+    fn threads(&self) -> usize;
+
+    /// Runs the tasks, returning once every one of them is done
+    fn run<'a>(&self, tasks: Vec<Box<dyn FnOnce() + Send + 'a>>);
+}
+
+/// Runs the tasks one after another on the calling thread, so stepping never starts threads
+pub struct Serial;
+
+impl Executor for Serial {
+    fn threads(&self) -> usize {
+        1
+    }
+
+    fn run<'a>(&self, tasks: Vec<Box<dyn FnOnce() + Send + 'a>>) {
+        for task in tasks {
+            task();
+        }
+    }
+}
+
+/// Runs every task on a thread of its own, started for every step. Only worth it for grids large
+/// enough that a step takes much longer than starting the threads.
+pub struct Threads(pub usize);
+
+impl Default for Threads {
+    /// As many threads as there are cores
+    fn default() -> Self {
+        Self(thread::available_parallelism().map_or(1, NonZeroUsize::get))
+    }
+}
+
+impl Executor for Threads {
+    fn threads(&self) -> usize {
+        self.0
+    }
+
+    fn run<'a>(&self, tasks: Vec<Box<dyn FnOnce() + Send + 'a>>) {
+        thread::scope(|scope| {
+            for task in tasks {
+                scope.spawn(task);
+            }
+        });
+    }
+}
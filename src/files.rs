@@ -0,0 +1,298 @@
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use bevy::prelude::*;
+use game_of_life::hashlife::HashLife;
+use game_of_life::pattern::{macrocell, Format, Pattern, PatternError};
+use game_of_life::rule::Rule;
+use game_of_life::ruletable::RuleTable;
+use game_of_life::sparse::SparseGrid;
+use game_of_life::state::{Coord, DenseGrid, GridSize, StateGrid};
+use game_of_life::topology::{split_rulestring, BoundedGrid};
+use game_of_life::undo::History;
+use game_of_life::Universe;
+
+use crate::config::Config;
+use crate::controls::{Action, Controls};
+use crate::grid::ViewOrigin;
+use crate::startup::check_unbounded;
+
+/// Loads the pattern file and saves the current cells to it
+pub struct PatternFilePlugin;
+
+impl Plugin for PatternFilePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PatternPath>()
+            .add_system(handle_file_keys);
+    }
+}
+
+/// The file patterns are loaded from and saved to. The extension selects the file format.
+pub struct PatternPath(pub PathBuf);
+
+impl Default for PatternPath {
+    fn default() -> Self {
+        Self(PathBuf::from("pattern.rle"))
+    }
+}
+
+fn handle_file_keys(
+    controls: Controls,
+    path: Res<PatternPath>,
+    mut grid_size: ResMut<GridSize>,
+    mut view_origin: ResMut<ViewOrigin>,
+    mut universe: ResMut<Universe>,
+    mut history: ResMut<History>,
+    mut config: ResMut<Config>,
+) {
+    if controls.just_released(Action::Open) {
+        let rule = universe.rule().clone();
+        let topology = universe.topology();
+        let mut size = *grid_size;
+        match open_pattern(&path.0, &mut view_origin, &mut universe, &mut size) {
+            Ok(replaced) => {
+                if size != *grid_size {
+                    *grid_size = size;
+                }
+                history.record_load(replaced, rule, topology);
+                info!("Loaded {}", path.0.display());
+                if config.pattern.as_ref() != Some(&path.0) {
+                    config.pattern = Some(path.0.clone());
+                }
+            }
+            Err(err) => error!("Could not load {}: {}", path.0.display(), err),
+        }
+    }
+    if controls.just_released(Action::Save) {
+        match save_pattern(&path.0, &universe) {
+            Ok(()) => info!("Saved {}", path.0.display()),
+            Err(err) => error!("Could not save {}: {}", path.0.display(), err),
+        }
+    }
+}
+
+/// Replaces the cells with the pattern file and switches to its rule, picking the format from
+/// the extension. Patterns are centered on the board, macrocell patterns on the origin. Returns
+/// the cells that were replaced. Files that can't be loaded leave everything as it was.
+pub fn open_pattern(
+    path: &Path,
+    view_origin: &mut ViewOrigin,
+    universe: &mut Universe,
+    grid_size: &mut GridSize,
+) -> Result<StateGrid, Box<dyn Error>> {
+    if is_macrocell(path) {
+        load_macrocell(path, view_origin, universe, grid_size)
+    } else {
+        load_pattern(path, view_origin, universe, grid_size)
+    }
+}
+
+/// The directory searched for .rule files after the directory of the pattern
+const RULES_DIR: &str = "rules";
+
+/// Parses a rulestring, or loads the rule table of that name from a .rule file like
+/// "WireWorld.rule" next to the pattern or in the rules directory
+pub fn find_rule(name: &str, pattern_dir: &Path) -> Result<Rule, Box<dyn Error>> {
+    let parse_error = match name.parse() {
+        Ok(rule) => return Ok(rule),
+        Err(err) => err,
+    };
+    let file_name = format!("{}.rule", name);
+    for dir in [pattern_dir, Path::new(RULES_DIR)] {
+        let path = dir.join(&file_name);
+        if path.is_file() {
+            let table: RuleTable = fs::read_to_string(&path)?.parse()?;
+            info!("Loaded the rule table {}", path.display());
+            return Ok(Rule::Table(Arc::new(table)));
+        }
+    }
+    Err(Box::new(parse_error))
+}
+
+/// The rule of a rulestring and the bounded grid of a suffix like ":T50,50", checked without
+/// changing anything
+fn parse_rulestring(
+    rulestring: &str,
+    pattern_dir: &Path,
+) -> Result<(Rule, Option<BoundedGrid>), Box<dyn Error>> {
+    let (rule_part, bounded_grid) = split_rulestring(rulestring);
+    let bounded_grid = bounded_grid
+        .map(|bounded_grid| bounded_grid.parse::<BoundedGrid>())
+        .transpose()?;
+    Ok((find_rule(rule_part.trim(), pattern_dir)?, bounded_grid))
+}
+
+/// Switches to the rule, and to the topology and size of the bounded grid if there is one
+fn apply_rule(
+    rule: Rule,
+    bounded_grid: Option<BoundedGrid>,
+    universe: &mut Universe,
+    grid_size: &mut GridSize,
+) {
+    universe.set_rule(rule);
+    if let Some(bounded_grid) = bounded_grid {
+        universe.set_topology(bounded_grid.topology);
+        *grid_size = bounded_grid.size(*grid_size);
+    }
+}
+
+/// Switches to the rule of a rulestring. Returns the bounded grid of a suffix like ":T50,50",
+/// after switching to its topology and size. Nothing changes unless both are valid.
+pub fn apply_rulestring(
+    rulestring: &str,
+    pattern_dir: &Path,
+    universe: &mut Universe,
+    grid_size: &mut GridSize,
+) -> Result<Option<BoundedGrid>, Box<dyn Error>> {
+    let (rule, bounded_grid) = parse_rulestring(rulestring, pattern_dir)?;
+    apply_rule(rule, bounded_grid, universe, grid_size);
+    Ok(bounded_grid)
+}
+
+/// Replaces the cells with the pattern centered on the board and switches to the pattern's rule.
+/// Patterns on a bounded grid switch to a bounded grid of that topology and size. The rule, the
+/// bounded grid and the states of the cells are checked before anything changes.
+fn load_pattern(
+    path: &Path,
+    view_origin: &mut ViewOrigin,
+    universe: &mut Universe,
+    grid_size: &mut GridSize,
+) -> Result<StateGrid, Box<dyn Error>> {
+    let pattern = pattern_format(path)?.read(&fs::read_to_string(path)?)?;
+    let (rule, bounded_grid) = match &pattern.rule {
+        Some(rulestring) => parse_rulestring(rulestring, pattern_dir(path))?,
+        None => (universe.rule().clone(), None),
+    };
+    let bounded = bounded_grid.is_some() || matches!(universe.grid(), StateGrid::Bounded(_));
+    if !bounded {
+        check_unbounded(&rule)?;
+    }
+    let states = rule.states();
+    if let Some((_, state)) = pattern
+        .cells
+        .iter()
+        .find(|(_, state)| u16::from(*state) >= states)
+    {
+        return Err(format!("state {} is not a state of {}", state, rule).into());
+    }
+    let size = bounded_grid.map_or(*grid_size, |bounded_grid| bounded_grid.size(*grid_size));
+    let mut grid = match universe.grid() {
+        StateGrid::Unbounded(_) if !bounded => StateGrid::Unbounded(SparseGrid::default()),
+        StateGrid::HashLife(_) if !bounded => StateGrid::HashLife(HashLife::default()),
+        _ => StateGrid::Bounded(DenseGrid::new(size)),
+    };
+    // Bounded grids are shown from the origin
+    let origin = if bounded_grid.is_some() {
+        Coord::new(0, 0)
+    } else {
+        view_origin.0
+    };
+    let center = Coord::new(
+        origin.x + size.width as i64 / 2,
+        origin.y + size.height as i64 / 2,
+    );
+    for (pos, state) in pattern.cells_around(center) {
+        grid.set(pos, state);
+    }
+    // Bounded grids leave out the cells that don't fit
+    let left_out = (pattern.cells.len() as u64).saturating_sub(grid.population());
+    if left_out > 0 {
+        warn!(
+            "{} is larger than the {}x{} grid, {} cells outside of it were left out",
+            path.display(),
+            size.width,
+            size.height,
+            left_out
+        );
+    }
+    apply_rule(rule, bounded_grid, universe, grid_size);
+    view_origin.0 = origin;
+    Ok(std::mem::replace(universe.grid_mut(), grid))
+}
+
+/// Replaces the cells with the macrocell pattern, switching to the HashLife engine. HashLife
+/// universes are unbounded, a bounded grid in the rule only applies to the bounded engine.
+fn load_macrocell(
+    path: &Path,
+    view_origin: &mut ViewOrigin,
+    universe: &mut Universe,
+    grid_size: &mut GridSize,
+) -> Result<StateGrid, Box<dyn Error>> {
+    let macrocell = macrocell::read(&fs::read_to_string(path)?)?;
+    let (rule, bounded_grid) = match &macrocell.rule {
+        Some(rulestring) => parse_rulestring(rulestring, pattern_dir(path))?,
+        None => (universe.rule().clone(), None),
+    };
+    check_unbounded(&rule)?;
+    apply_rule(rule, bounded_grid, universe, grid_size);
+    // The view moves to the pattern instead of the pattern to the view
+    view_origin.0 = Coord::new(
+        -(grid_size.width as i64) / 2,
+        -(grid_size.height as i64) / 2,
+    );
+    Ok(std::mem::replace(
+        universe.grid_mut(),
+        StateGrid::HashLife(macrocell.universe),
+    ))
+}
+
+/// The rule, followed by the bounded grid for bounded engines
+fn rulestring(universe: &Universe) -> String {
+    match universe.grid() {
+        StateGrid::Bounded(grid) => {
+            let size = grid.size();
+            let bounded_grid = BoundedGrid {
+                topology: universe.topology(),
+                width: size.width,
+                height: size.height,
+            };
+            format!("{}:{}", universe.rule(), bounded_grid)
+        }
+        _ => universe.rule().to_string(),
+    }
+}
+
+pub fn save_pattern(path: &Path, universe: &Universe) -> Result<(), Box<dyn Error>> {
+    let rulestring = rulestring(universe);
+    if is_macrocell(path) {
+        let contents = match universe.grid() {
+            StateGrid::HashLife(hashlife) => macrocell::write(hashlife, &rulestring),
+            grid => {
+                let cells = grid.cells();
+                // HashLife and its files only have live and dead cells
+                if let Some((_, state)) = cells.iter().find(|(_, state)| *state != 1) {
+                    return Err(Box::new(PatternError::UnsupportedState(*state)));
+                }
+                let mut hashlife = HashLife::default();
+                for (pos, _) in cells {
+                    hashlife.set(pos, true);
+                }
+                hashlife.set_generation(grid.generation());
+                macrocell::write(&hashlife, &rulestring)
+            }
+        };
+        fs::write(path, contents)?;
+        return Ok(());
+    }
+    let mut pattern = Pattern::from_cells(&universe.grid().cells());
+    pattern.rule = Some(rulestring);
+    fs::write(path, pattern_format(path)?.write(&pattern)?)?;
+    Ok(())
+}
+
+pub fn pattern_dir(path: &Path) -> &Path {
+    path.parent().unwrap_or_else(|| Path::new(""))
+}
+
+fn is_macrocell(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("mc"))
+}
+
+fn pattern_format(path: &Path) -> Result<Format, String> {
+    Format::from_path(path).ok_or_else(|| {
+        "unknown file extension, expected .rle, .cells, .lif, .life or .mc".to_string()
+    })
+}
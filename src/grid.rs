@@ -1,5 +1,11 @@
+use std::marker::PhantomData;
+
+use bevy::ecs::system::SystemParam;
 use bevy::prelude::*;
-use crate::{GRID_WIDTH, GRID_HEIGHT};
+
+use game_of_life::rule::Rule;
+use game_of_life::state::{BoundingBox, Coord, GridSize};
+use game_of_life::Universe;
 
 pub struct GridPlugin;
 
@@ -10,7 +16,138 @@ impl Plugin for GridPlugin {
             SystemSet::new()
                 .with_system(position_translation)
                 .with_system(size_scaling),
-        );
+        )
+        .add_system(pick_render_mode.label("render_mode").after("visible_cells"));
+    }
+}
+
+/// Boards with more cells than this are drawn as a texture by default
+const MAX_SPRITE_CELLS: usize = 100 * 100;
+
+/// How the board is drawn: with one sprite entity per cell, or as a single texture with one pixel
+/// per cell, which stays fast on large boards
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum RenderMode {
+    Sprites,
+    Texture,
+}
+
+impl RenderMode {
+    /// The mode that suits a board of the given size
+    pub fn for_size(size: GridSize) -> Self {
+        if size.width * size.height <= MAX_SPRITE_CELLS {
+            Self::Sprites
+        } else {
+            Self::Texture
+        }
+    }
+}
+
+/// Picks the render mode that suits the visible cells when the grid size changes, or when zooming
+/// an unbounded grid crosses the number of cells sprites are fast for
+fn pick_render_mode(
+    view: BoardView,
+    mut render_mode: ResMut<RenderMode>,
+    mut suited_mode: Local<Option<RenderMode>>,
+) {
+    let suited = RenderMode::for_size(view.visible.size());
+    if view.grid_size.is_changed() || *suited_mode != Some(suited) {
+        *suited_mode = Some(suited);
+        *render_mode = suited;
+    }
+}
+
+/// The colors of the cells, as the config sets them
+pub struct CellColors {
+    pub dead: Color,
+    pub alive: Color,
+    /// Dying cells of Generations rules fade from the first color to the second one
+    pub dying: [Color; 2],
+}
+
+/// The color of a cell in the given state. Rule tables may pick their own colors.
+pub fn cell_color(state: u8, rule: &Rule, colors: &CellColors) -> Color {
+    if let Rule::Table(table) = rule {
+        if let Some(Some([r, g, b])) = table.colors.get(state as usize) {
+            return Color::rgb_u8(*r, *g, *b);
+        }
+    }
+    match state {
+        0 => colors.dead,
+        1 => colors.alive,
+        _ => {
+            // The last dying state is `states - 1`
+            let last = rule.states().saturating_sub(3).max(1);
+            let t = ((state - 2) as f32 / last as f32).min(1.0);
+            let [r0, g0, b0, _] = colors.dying[0].as_rgba_f32();
+            let [r1, g1, b1, _] = colors.dying[1].as_rgba_f32();
+            Color::rgb(r0 + (r1 - r0) * t, g0 + (g1 - g0) * t, b0 + (b1 - b0) * t)
+        }
+    }
+}
+
+/// The cell shown in the bottom left corner of the board while the camera is not moved. Bounded
+/// grids are always shown from the origin, unbounded grids move it to loaded patterns and to the
+/// cells the camera frames, which keeps the world coordinates around the camera small.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct ViewOrigin(pub Coord);
+
+impl Default for ViewOrigin {
+    fn default() -> Self {
+        Self(Coord::new(0, 0))
+    }
+}
+
+/// The cells that are drawn. Bounded grids always draw the whole board, unbounded grids draw the
+/// part of the plane the camera shows, which follows it as it pans and zooms.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct VisibleCells(pub BoundingBox);
+
+impl VisibleCells {
+    /// The board of the given size, starting at the view origin
+    pub fn board(view_origin: ViewOrigin, grid_size: GridSize) -> Self {
+        let origin = view_origin.0;
+        Self(BoundingBox {
+            min: origin,
+            max: Coord::new(
+                origin.x + grid_size.width as i64 - 1,
+                origin.y + grid_size.height as i64 - 1,
+            ),
+        })
+    }
+
+    /// How many columns and rows of cells are drawn
+    pub fn size(&self) -> GridSize {
+        GridSize {
+            width: self.0.width() as usize,
+            height: self.0.height() as usize,
+        }
+    }
+
+    /// The board position of the cell `x` columns and `y` rows from the bottom left corner
+    pub fn position(&self, view_origin: ViewOrigin, x: usize, y: usize) -> Position {
+        Position {
+            x: (self.0.min.x - view_origin.0.x) as i32 + x as i32,
+            y: (self.0.min.y - view_origin.0.y) as i32 + y as i32,
+        }
+    }
+}
+
+/// The board and the cells drawn on it
+#[derive(SystemParam)]
+pub struct BoardView<'w, 's> {
+    windows: Res<'w, Windows>,
+    pub grid_size: Res<'w, GridSize>,
+    pub origin: Res<'w, ViewOrigin>,
+    pub visible: Res<'w, VisibleCells>,
+    #[system_param(ignore)]
+    marker: PhantomData<&'s ()>,
+}
+
+impl<'w, 's> BoardView<'w, 's> {
+    /// The side of a cell in world units in the primary window
+    pub fn cell_size(&self) -> f32 {
+        cell_size(self.windows.get_primary().unwrap(), *self.grid_size)
     }
 }
 
@@ -26,6 +163,10 @@ pub struct Size {
     height: f32,
 }
 impl Size {
+    pub fn new(width: f32, height: f32) -> Self {
+        Self { width, height }
+    }
+
     pub fn square(x: f32) -> Self {
         Self {
             width: x,
@@ -34,28 +175,58 @@ impl Size {
     }
 }
 
-fn size_scaling(windows: Res<Windows>, mut q: Query<(&Size, &mut Transform)>) {
-    let window = windows.get_primary().unwrap();
+/// The side of a cell in world units, the same for both axes. At the camera's default zoom the
+/// board fills the window as far as its aspect ratio allows.
+pub fn cell_size(window: &Window, grid_size: GridSize) -> f32 {
+    (window.width() / grid_size.width as f32).min(window.height() / grid_size.height as f32)
+}
+
+/// The world coordinates of the bottom left corner of a board cell. The board is centered on the
+/// world origin.
+pub fn cell_corner(x: f32, y: f32, grid_size: GridSize, cell_size: f32) -> Vec2 {
+    Vec2::new(
+        (x - grid_size.width as f32 / 2.0) * cell_size,
+        (y - grid_size.height as f32 / 2.0) * cell_size,
+    )
+}
+
+fn size_scaling(
+    windows: Res<Windows>,
+    grid_size: Res<GridSize>,
+    mut q: Query<(&Size, &mut Transform)>,
+) {
+    let cell_size = cell_size(windows.get_primary().unwrap(), *grid_size);
     for (sprite_size, mut transform) in q.iter_mut() {
         transform.scale = Vec3::new(
-            sprite_size.width / GRID_WIDTH as f32 * window.width() as f32,
-            sprite_size.height / GRID_HEIGHT as f32 * window.height() as f32,
+            sprite_size.width * cell_size,
+            sprite_size.height * cell_size,
             1.0,
-            );
+        );
     }
 }
 
-fn position_translation(windows: Res<Windows>, mut q: Query<(&Position, &mut Transform)>) {
-    fn convert(pos: f32, bound_window: f32, bound_game: f32) -> f32 {
-        let tile_size = bound_window / bound_game;
-        pos / bound_game * bound_window - (bound_window / 2.) + (tile_size / 2.)
+/// How many cells to the right the cells of row `y` are drawn. Hexagonal cells are drawn with
+/// every row half a cell further right than the one below it, so each cell touches its six
+/// neighbors. The middle row stays in place.
+pub fn row_offset(y: i32, height: usize, hexagonal: bool) -> f32 {
+    if hexagonal {
+        (y as f32 - (height as f32 - 1.0) / 2.0) / 2.0
+    } else {
+        0.0
     }
-    let window = windows.get_primary().unwrap();
+}
+
+fn position_translation(
+    windows: Res<Windows>,
+    grid_size: Res<GridSize>,
+    universe: Res<Universe>,
+    mut q: Query<(&Position, &mut Transform)>,
+) {
+    let cell_size = cell_size(windows.get_primary().unwrap(), *grid_size);
+    let hexagonal = universe.rule().is_hexagonal();
     for (pos, mut transform) in q.iter_mut() {
-        transform.translation = Vec3::new(
-            convert(pos.x as f32, window.width() as f32, GRID_WIDTH as f32),
-            convert(pos.y as f32, window.height() as f32, GRID_HEIGHT as f32),
-            0.0,
-            );
+        let x = pos.x as f32 + row_offset(pos.y, grid_size.height, hexagonal);
+        let corner = cell_corner(x, pos.y as f32, *grid_size, cell_size);
+        transform.translation = (corner + cell_size / 2.0).extend(0.0);
     }
 }
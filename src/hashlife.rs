@@ -0,0 +1,500 @@
+use std::collections::HashMap;
+
+use crate::isotropic;
+use crate::rule::{LifeLike, TransitionTable};
+use crate::state::{BoundingBox, Coord};
+
+pub type NodeId = u32;
+
+const DEAD: NodeId = 0;
+const ALIVE: NodeId = 1;
+const NO_RESULT: NodeId = NodeId::MAX;
+
+/// Once the node arena grows past this many nodes the unreachable ones are collected
+const GC_THRESHOLD: usize = 1 << 21;
+
+/// The level of the smallest root. Levels 0 and 1 can't be stepped and level 2 has no room for the
+/// pattern to grow.
+const MIN_ROOT_LEVEL: u8 = 3;
+
+/// The largest root level before coordinates stop fitting into an i64
+const MAX_ROOT_LEVEL: u8 = 62;
+
+/// A square of 2^level cells. Levels above 0 are made up of four children of the level below in
+/// the order north west, north east, south west, south east, north being towards positive y.
+#[derive(Clone, Copy)]
+struct Node {
+    level: u8,
+    children: [NodeId; 4],
+    population: u64,
+    /// The center of this node advanced by 2^min(step_log2, level - 2) generations
+    result: NodeId,
+}
+
+/// An unbounded universe stored as a quadtree of canonicalized nodes, which memoizes the future
+/// of every node it has seen so that repetitive patterns can be advanced by huge numbers of
+/// generations at once.
+///
+/// The root is centered on the origin, so a root of level `n` covers the cells from -2^(n-1) to
+/// 2^(n-1) - 1 along both axes.
+#[derive(Clone)]
+pub struct HashLife {
+    nodes: Vec<Node>,
+    cache: HashMap<[NodeId; 4], NodeId>,
+    /// The empty node of each level
+    empty: Vec<NodeId>,
+    root: NodeId,
+    /// The rule and step size the memoized results were computed for
+    rule: TransitionTable,
+    step_log2: u32,
+    generation: u64,
+}
+
+impl Default for HashLife {
+    fn default() -> Self {
+        let leaf = |population| Node {
+            level: 0,
+            children: [DEAD; 4],
+            population,
+            result: NO_RESULT,
+        };
+        let mut hashlife = Self {
+            nodes: vec![leaf(0), leaf(1)],
+            cache: HashMap::new(),
+            empty: vec![DEAD],
+            root: DEAD,
+            rule: LifeLike::default().transition_table(),
+            step_log2: 0,
+            generation: 0,
+        };
+        hashlife.root = hashlife.empty(MIN_ROOT_LEVEL);
+        hashlife
+    }
+}
+
+impl HashLife {
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    pub fn set_generation(&mut self, generation: u64) {
+        self.generation = generation;
+    }
+
+    pub fn population(&self) -> u64 {
+        self.nodes[self.root as usize].population
+    }
+
+    pub fn root(&self) -> NodeId {
+        self.root
+    }
+
+    /// Replaces the whole universe with the node, which is centered on the origin like any root
+    /// and expanded to the minimum root size if it is smaller. The node can't be a single cell.
+    pub fn set_root(&mut self, id: NodeId) {
+        self.root = id;
+        while self.level(self.root) < MIN_ROOT_LEVEL {
+            self.root = self.expand(self.root);
+        }
+    }
+
+    pub fn leaf(alive: bool) -> NodeId {
+        if alive {
+            ALIVE
+        } else {
+            DEAD
+        }
+    }
+
+    pub fn level(&self, id: NodeId) -> u8 {
+        self.nodes[id as usize].level
+    }
+
+    pub fn children(&self, id: NodeId) -> [NodeId; 4] {
+        self.nodes[id as usize].children
+    }
+
+    pub fn node_population(&self, id: NodeId) -> u64 {
+        self.nodes[id as usize].population
+    }
+
+    /// The canonical node with the given children, which all have to be of the same level
+    pub fn join(&mut self, children: [NodeId; 4]) -> NodeId {
+        if let Some(id) = self.cache.get(&children) {
+            return *id;
+        }
+        let node = Node {
+            level: self.level(children[0]) + 1,
+            children,
+            population: children
+                .iter()
+                .map(|c| self.nodes[*c as usize].population)
+                .sum(),
+            result: NO_RESULT,
+        };
+        let id = self.nodes.len() as NodeId;
+        self.nodes.push(node);
+        self.cache.insert(children, id);
+        id
+    }
+
+    pub fn empty(&mut self, level: u8) -> NodeId {
+        while self.empty.len() <= level as usize {
+            let below = *self.empty.last().unwrap();
+            let node = self.join([below; 4]);
+            self.empty.push(node);
+        }
+        self.empty[level as usize]
+    }
+
+    /// Surrounds the node with empty space, doubling its size while keeping it centered
+    fn expand(&mut self, id: NodeId) -> NodeId {
+        let [nw, ne, sw, se] = self.children(id);
+        let e = self.empty(self.level(id) - 1);
+        let nw = self.join([e, e, e, nw]);
+        let ne = self.join([e, e, ne, e]);
+        let sw = self.join([e, sw, e, e]);
+        let se = self.join([se, e, e, e]);
+        self.join([nw, ne, sw, se])
+    }
+
+    /// The node of half the size at the center of `id`
+    fn center(&mut self, id: NodeId) -> NodeId {
+        let [nw, ne, sw, se] = self.children(id);
+        self.join([
+            self.children(nw)[3],
+            self.children(ne)[2],
+            self.children(sw)[1],
+            self.children(se)[0],
+        ])
+    }
+
+    /// The half-coordinate, distance from the origin, of the root's edges
+    fn root_radius(&self) -> i64 {
+        1 << (self.level(self.root) - 1)
+    }
+
+    pub fn get(&self, pos: Coord) -> bool {
+        let radius = self.root_radius();
+        if pos.x < -radius || pos.x >= radius || pos.y < -radius || pos.y >= radius {
+            return false;
+        }
+        let (mut x, mut y) = (pos.x + radius, pos.y + radius);
+        let mut id = self.root;
+        while self.level(id) > 0 {
+            if self.nodes[id as usize].population == 0 {
+                return false;
+            }
+            let half = 1 << (self.level(id) - 1);
+            let (right, top) = (x >= half, y >= half);
+            id = self.children(id)[quadrant(right, top)];
+            x -= if right { half } else { 0 };
+            y -= if top { half } else { 0 };
+        }
+        id == ALIVE
+    }
+
+    pub fn set(&mut self, pos: Coord, alive: bool) {
+        while pos.x.abs() >= self.root_radius() - 1 || pos.y.abs() >= self.root_radius() - 1 {
+            if self.level(self.root) >= MAX_ROOT_LEVEL {
+                return;
+            }
+            self.root = self.expand(self.root);
+        }
+        let radius = self.root_radius();
+        self.root = self.set_in(self.root, pos.x + radius, pos.y + radius, alive);
+    }
+
+    /// Sets a cell in a node where `x` and `y` are relative to the south west corner
+    fn set_in(&mut self, id: NodeId, x: i64, y: i64, alive: bool) -> NodeId {
+        if self.level(id) == 0 {
+            return if alive { ALIVE } else { DEAD };
+        }
+        let half = 1 << (self.level(id) - 1);
+        let (right, top) = (x >= half, y >= half);
+        let mut children = self.children(id);
+        let i = quadrant(right, top);
+        children[i] = self.set_in(
+            children[i],
+            x - if right { half } else { 0 },
+            y - if top { half } else { 0 },
+            alive,
+        );
+        self.join(children)
+    }
+
+    pub fn live_cells(&self) -> Vec<Coord> {
+        let radius = self.root_radius();
+        self.live_cells_in(BoundingBox {
+            min: Coord::new(-radius, -radius),
+            max: Coord::new(radius - 1, radius - 1),
+        })
+    }
+
+    /// The live cells inside of `area`, skipping the nodes outside of it
+    pub fn live_cells_in(&self, area: BoundingBox) -> Vec<Coord> {
+        let mut cells = vec![];
+        let radius = self.root_radius();
+        self.collect_cells(self.root, -radius, -radius, &area, &mut cells);
+        cells
+    }
+
+    fn collect_cells(
+        &self,
+        id: NodeId,
+        x: i64,
+        y: i64,
+        area: &BoundingBox,
+        cells: &mut Vec<Coord>,
+    ) {
+        let node = self.nodes[id as usize];
+        let size = 1 << node.level;
+        if node.population == 0
+            || x > area.max.x
+            || y > area.max.y
+            || x + size - 1 < area.min.x
+            || y + size - 1 < area.min.y
+        {
+            return;
+        }
+        if node.level == 0 {
+            cells.push(Coord::new(x, y));
+            return;
+        }
+        let half = size / 2;
+        let [nw, ne, sw, se] = node.children;
+        self.collect_cells(nw, x, y + half, area, cells);
+        self.collect_cells(ne, x + half, y + half, area, cells);
+        self.collect_cells(sw, x, y, area, cells);
+        self.collect_cells(se, x + half, y, area, cells);
+    }
+
+    pub fn bounding_box(&self) -> Option<BoundingBox> {
+        let mut bounding_box = None;
+        let radius = self.root_radius();
+        self.extend_bounding_box(self.root, -radius, -radius, &mut bounding_box);
+        bounding_box
+    }
+
+    /// Grows the box to include the live cells of the node, skipping nodes that lie completely
+    /// inside of it
+    fn extend_bounding_box(
+        &self,
+        id: NodeId,
+        x: i64,
+        y: i64,
+        bounding_box: &mut Option<BoundingBox>,
+    ) {
+        let node = self.nodes[id as usize];
+        if node.population == 0 {
+            return;
+        }
+        let size = 1 << node.level;
+        if let Some(bounding_box) = bounding_box {
+            if bounding_box.contains(Coord::new(x, y))
+                && bounding_box.contains(Coord::new(x + size - 1, y + size - 1))
+            {
+                return;
+            }
+        }
+        if node.level == 0 {
+            let pos = Coord::new(x, y);
+            *bounding_box =
+                Some(bounding_box.map_or(BoundingBox { min: pos, max: pos }, |b| b.including(pos)));
+            return;
+        }
+        let half = size / 2;
+        let [nw, ne, sw, se] = node.children;
+        self.extend_bounding_box(nw, x, y + half, bounding_box);
+        self.extend_bounding_box(ne, x + half, y + half, bounding_box);
+        self.extend_bounding_box(sw, x, y, bounding_box);
+        self.extend_bounding_box(se, x + half, y, bounding_box);
+    }
+
+    /// Advances the universe by 2^`step_log2` generations of the rule given by its transition
+    /// table. Only two state rules on the 8 neighbors around a cell have one.
+    pub fn step_pow2(&mut self, rule: &TransitionTable, step_log2: u32) {
+        let step_log2 = step_log2.min(MAX_ROOT_LEVEL as u32 - 4);
+        if *rule != self.rule || step_log2 != self.step_log2 {
+            self.rule = *rule;
+            self.step_log2 = step_log2;
+            for node in &mut self.nodes {
+                node.result = NO_RESULT;
+            }
+        }
+
+        // The pattern has to lie within the center of the root so it can't grow past the result
+        while (self.level(self.root) as u32) < step_log2 + 2 || !self.is_padded(self.root) {
+            self.root = self.expand(self.root);
+        }
+        let expanded = self.expand(self.root);
+        self.root = self.result(expanded);
+        self.generation += 1 << step_log2;
+
+        // Shrink the root back down so lookups stay cheap
+        while self.level(self.root) > MIN_ROOT_LEVEL {
+            let center = self.center(self.root);
+            if !self.is_padded(center) {
+                break;
+            }
+            self.root = center;
+        }
+
+        if self.nodes.len() > GC_THRESHOLD {
+            self.collect_garbage();
+        }
+    }
+
+    /// Whether all live cells of the node lie within its center
+    fn is_padded(&mut self, id: NodeId) -> bool {
+        let center = self.center(id);
+        self.nodes[center as usize].population == self.nodes[id as usize].population
+    }
+
+    /// The center of the node advanced by 2^min(step_log2, level - 2) generations
+    fn result(&mut self, id: NodeId) -> NodeId {
+        let node = self.nodes[id as usize];
+        if node.result != NO_RESULT {
+            return node.result;
+        }
+        let result = if node.population == 0 {
+            self.empty(node.level - 1)
+        } else if node.level == 2 {
+            self.step_level_2(id)
+        } else {
+            self.step_recursive(id)
+        };
+        self.nodes[id as usize].result = result;
+        result
+    }
+
+    fn step_recursive(&mut self, id: NodeId) -> NodeId {
+        let level = self.level(id);
+        // Advance both halves of the step when the full 2^(level - 2) generations are wanted,
+        // only the second one otherwise
+        let full_speed = self.step_log2 + 2 >= level as u32;
+        let [a, b, c, d] = self.children(id);
+        let [a_nw, a_ne, a_sw, a_se] = self.children(a);
+        let [b_nw, b_ne, b_sw, b_se] = self.children(b);
+        let [c_nw, c_ne, c_sw, c_se] = self.children(c);
+        let [d_nw, d_ne, d_sw, d_se] = self.children(d);
+
+        let first_half = |hashlife: &mut Self, children: [NodeId; 4]| {
+            let node = hashlife.join(children);
+            if full_speed {
+                hashlife.result(node)
+            } else {
+                hashlife.center(node)
+            }
+        };
+        let n00 = first_half(self, [a_nw, a_ne, a_sw, a_se]);
+        let n01 = first_half(self, [a_ne, b_nw, a_se, b_sw]);
+        let n02 = first_half(self, [b_nw, b_ne, b_sw, b_se]);
+        let n10 = first_half(self, [a_sw, a_se, c_nw, c_ne]);
+        let n11 = first_half(self, [a_se, b_sw, c_ne, d_nw]);
+        let n12 = first_half(self, [b_sw, b_se, d_nw, d_ne]);
+        let n20 = first_half(self, [c_nw, c_ne, c_sw, c_se]);
+        let n21 = first_half(self, [c_ne, d_nw, c_se, d_sw]);
+        let n22 = first_half(self, [d_nw, d_ne, d_sw, d_se]);
+
+        let second_half = |hashlife: &mut Self, children: [NodeId; 4]| {
+            let node = hashlife.join(children);
+            hashlife.result(node)
+        };
+        let nw = second_half(self, [n00, n01, n10, n11]);
+        let ne = second_half(self, [n01, n02, n11, n12]);
+        let sw = second_half(self, [n10, n11, n20, n21]);
+        let se = second_half(self, [n11, n12, n21, n22]);
+        self.join([nw, ne, sw, se])
+    }
+
+    /// Steps the 4x4 cells of a level 2 node by one generation, returning its 2x2 center
+    fn step_level_2(&mut self, id: NodeId) -> NodeId {
+        // cells[y][x] with y pointing north
+        let mut cells = [[false; 4]; 4];
+        for (i, child) in self.children(id).into_iter().enumerate() {
+            let (cx, cy) = quadrant_offset(i, 4);
+            for (j, leaf) in self.children(child).into_iter().enumerate() {
+                let (lx, ly) = quadrant_offset(j, 2);
+                cells[(cy + ly) as usize][(cx + lx) as usize] = leaf == ALIVE;
+            }
+        }
+        let mut next = [DEAD; 4];
+        for (i, cell) in next.iter_mut().enumerate() {
+            let (dx, dy) = quadrant_offset(i, 2);
+            let (x, y) = (1 + dx, 1 + dy);
+            let neighborhood =
+                isotropic::neighborhood(|nx, ny| cells[(y + ny) as usize][(x + nx) as usize]);
+            if self.rule.get(cells[y as usize][x as usize], neighborhood) {
+                *cell = ALIVE;
+            }
+        }
+        self.join(next)
+    }
+
+    /// Rebuilds the node arena with only the nodes reachable from the root. Memoized results are
+    /// kept as long as the node they point to survives.
+    pub fn collect_garbage(&mut self) {
+        let mut reachable = vec![false; self.nodes.len()];
+        let mut stack = self.empty.clone();
+        stack.extend([DEAD, ALIVE, self.root]);
+        while let Some(id) = stack.pop() {
+            if std::mem::replace(&mut reachable[id as usize], true) {
+                continue;
+            }
+            let node = self.nodes[id as usize];
+            if node.level > 0 {
+                stack.extend(node.children);
+            }
+        }
+
+        // Children are always created before their parents, so a single pass in order is enough
+        let mut new_ids = vec![NO_RESULT; self.nodes.len()];
+        let mut nodes = Vec::with_capacity(reachable.iter().filter(|r| **r).count());
+        let mut cache = HashMap::with_capacity(nodes.capacity());
+        for (id, node) in self.nodes.iter().enumerate() {
+            if !reachable[id] {
+                continue;
+            }
+            let mut node = *node;
+            if node.level > 0 {
+                node.children = node.children.map(|c| new_ids[c as usize]);
+                cache.insert(node.children, nodes.len() as NodeId);
+            }
+            new_ids[id] = nodes.len() as NodeId;
+            nodes.push(node);
+        }
+        for node in &mut nodes {
+            if node.result != NO_RESULT {
+                node.result = new_ids[node.result as usize];
+            }
+        }
+        self.nodes = nodes;
+        self.cache = cache;
+        self.root = new_ids[self.root as usize];
+        for id in &mut self.empty {
+            *id = new_ids[*id as usize];
+        }
+    }
+}
+
+/// The index of the child in the given half of a node
+fn quadrant(right: bool, top: bool) -> usize {
+    match (right, top) {
+        (false, true) => 0,
+        (true, true) => 1,
+        (false, false) => 2,
+        (true, false) => 3,
+    }
+}
+
+/// The offset of the child with the given index from the south west corner of its parent
+fn quadrant_offset(index: usize, parent_size: i64) -> (i64, i64) {
+    let half = parent_size / 2;
+    match index {
+        0 => (0, half),
+        1 => (half, half),
+        2 => (0, 0),
+        _ => (half, 0),
+    }
+}
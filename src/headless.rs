@@ -0,0 +1,37 @@
+use std::error::Error;
+
+use crate::args::Options;
+use crate::config::Config;
+use crate::files::save_pattern;
+use crate::startup::{Engine, Startup};
+
+/// Runs the pattern or random soup for the given number of generations without opening a window
+/// and prints the generation, population and bounding box it ends with. Without a size or engine
+/// it runs on HashLife, or on the hash set engine for rules HashLife can't run. The config file is
+/// ignored, so runs only depend on the command line.
+pub fn run(options: &Options) -> Result<(), Box<dyn Error>> {
+    let engine = if options.size.is_some() {
+        Engine::Bounded
+    } else {
+        Engine::HashLife
+    };
+    let mut universe = Startup::new(options, &Config::default(), engine)?.universe;
+    universe.advance(options.generations);
+
+    println!("Generation {}", universe.generation());
+    println!("Population {}", universe.population());
+    if let Some(bounding_box) = universe.bounding_box() {
+        println!(
+            "Bounds {}x{} from ({}, {})",
+            bounding_box.width(),
+            bounding_box.height(),
+            bounding_box.min.x,
+            bounding_box.min.y,
+        );
+    }
+    if let Some(output) = &options.output {
+        save_pattern(output, &universe)?;
+        println!("Saved {}", output.display());
+    }
+    Ok(())
+}
@@ -0,0 +1,42 @@
+use bevy::prelude::*;
+use game_of_life::state::{GridSize, StateGrid};
+use game_of_life::undo::History;
+use game_of_life::Universe;
+
+use crate::controls::{Action, Controls};
+use crate::Paused;
+
+/// Undoes and redoes changes to the cells
+pub struct HistoryPlugin;
+
+impl Plugin for HistoryPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<History>()
+            .add_system(handle_history_keys);
+    }
+}
+
+/// Undoing or redoing a load may bring back a bounded grid of another size, the board follows it
+fn handle_history_keys(
+    controls: Controls,
+    mut history: ResMut<History>,
+    mut universe: ResMut<Universe>,
+    mut grid_size: ResMut<GridSize>,
+    mut paused: ResMut<Paused>,
+) {
+    let changed = if controls.just_released(Action::Undo) {
+        history.undo(&mut universe)
+    } else if controls.just_released(Action::Redo) {
+        history.redo(&mut universe)
+    } else {
+        false
+    };
+    if changed {
+        (*paused).0 = true;
+        if let StateGrid::Bounded(grid) = universe.grid() {
+            if grid.size() != *grid_size {
+                *grid_size = grid.size();
+            }
+        }
+    }
+}
@@ -0,0 +1,272 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::rule::{RuleParseError, TransitionTable};
+
+/// The neighbors clockwise from the north. Bit `i` of a neighborhood is the neighbor at
+/// `NEIGHBOR_BITS[i]`.
+pub const NEIGHBOR_BITS: [[i64; 2]; 8] = [
+    [0, 1],
+    [1, 1],
+    [1, 0],
+    [1, -1],
+    [0, -1],
+    [-1, -1],
+    [-1, 0],
+    [-1, 1],
+];
+
+/// Hensel's letters for 1 to 4 live neighbors, each with one arrangement of the neighbors as a
+/// neighborhood. The other arrangements of a letter are its rotations and reflections.
+const LETTERS: [&[(char, u8)]; 5] = [
+    &[],
+    &[('c', 0b0000_0010), ('e', 0b0000_0001)],
+    &[
+        ('c', 0b0000_1010),
+        ('e', 0b0000_0101),
+        ('k', 0b0000_1001),
+        ('a', 0b0000_0011),
+        ('i', 0b0001_0001),
+        ('n', 0b0010_0010),
+    ],
+    &[
+        ('c', 0b0010_1010),
+        ('e', 0b0001_0101),
+        ('k', 0b0010_0101),
+        ('a', 0b0000_0111),
+        ('i', 0b1000_0011),
+        ('n', 0b0000_1011),
+        ('y', 0b0010_1001),
+        ('q', 0b0010_0011),
+        ('j', 0b0100_0011),
+        ('r', 0b0001_0011),
+    ],
+    &[
+        ('c', 0b1010_1010),
+        ('e', 0b0101_0101),
+        ('k', 0b0100_1011),
+        ('a', 0b0000_1111),
+        ('i', 0b0001_1011),
+        ('n', 0b1000_1011),
+        ('y', 0b0010_1011),
+        ('q', 0b0010_0111),
+        ('j', 0b0101_0011),
+        ('r', 0b0001_0111),
+        ('t', 0b1001_0011),
+        ('w', 0b0110_0011),
+        ('z', 0b0011_0011),
+    ],
+];
+
+/// The letters for `count` live neighbors. 5 to 8 neighbors use the letters of 3 to 0 with the
+/// live and dead neighbors swapped.
+fn letters(count: usize) -> impl Iterator<Item = (char, u8)> {
+    let complement = if count > 4 { 0xff } else { 0 };
+    LETTERS[count.min(8 - count)]
+        .iter()
+        .map(move |(letter, neighborhood)| (*letter, neighborhood ^ complement))
+}
+
+/// The rotations and reflections of a neighborhood
+fn symmetries(neighborhood: u8) -> impl Iterator<Item = u8> {
+    // Mirrors the neighbors along the north-south axis
+    let reflected = (0..8).fold(0u8, |mirrored, i| {
+        mirrored | (((neighborhood >> i) & 1) << ((8 - i) % 8))
+    });
+    // Turning by a quarter moves every neighbor two places clockwise
+    (0..4).flat_map(move |turns| {
+        [
+            neighborhood.rotate_left(2 * turns),
+            reflected.rotate_left(2 * turns),
+        ]
+    })
+}
+
+/// The neighborhood of a cell as bits, from a function telling which neighbors are alive
+pub fn neighborhood(mut alive: impl FnMut(i64, i64) -> bool) -> u8 {
+    NEIGHBOR_BITS
+        .iter()
+        .enumerate()
+        .filter(|(_, [dx, dy])| alive(*dx, *dy))
+        .fold(0, |neighborhood, (i, _)| neighborhood | 1 << i)
+}
+
+/// An isotropic non-totalistic rule in Hensel notation, like "B2-a/S12". Besides the number of
+/// live neighbors it depends on how they are arranged, as long as the arrangement behaves the
+/// same when rotated or reflected. Rules with more than two `states` decay like Generations
+/// rules.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Isotropic {
+    pub table: TransitionTable,
+    pub states: u8,
+}
+
+impl Isotropic {
+    /// The state after `state`, for a cell with the live neighbors in `neighborhood`
+    pub fn next_cell(&self, state: u8, neighborhood: u8) -> u8 {
+        match state {
+            0 => self.table.get(false, neighborhood) as u8,
+            1 if self.table.get(true, neighborhood) => 1,
+            _ if state + 1 < self.states => state + 1,
+            _ => 0,
+        }
+    }
+
+    /// Whether only the number of live neighbors matters, so the rule is just a Life-like one.
+    /// The counts are returned as birth and survival conditions in that case.
+    pub fn totalistic_counts(&self) -> Option<([bool; 9], [bool; 9])> {
+        let mut counts = [[false; 9]; 2];
+        for (alive, counts) in counts.iter_mut().enumerate() {
+            for (count, included) in counts.iter_mut().enumerate() {
+                let mut outcomes = (0..=255u8)
+                    .filter(|neighborhood| neighborhood.count_ones() as usize == count)
+                    .map(|neighborhood| self.table.get(alive == 1, neighborhood));
+                let first = outcomes.next().unwrap();
+                if outcomes.any(|outcome| outcome != first) {
+                    return None;
+                }
+                *included = first;
+            }
+        }
+        Some((counts[0], counts[1]))
+    }
+
+    /// One section of the rulestring, the counts and letters leading to a live cell for cells that
+    /// are `alive` or not
+    fn write_section(&self, f: &mut fmt::Formatter, alive: bool) -> fmt::Result {
+        for count in 0..=8 {
+            let outcome = |neighborhood: u8| self.table.get(alive, neighborhood);
+            let mut letters: Vec<(char, bool)> = letters(count)
+                .map(|(letter, neighborhood)| (letter, outcome(neighborhood)))
+                .collect();
+            // Written alphabetically like most rulestrings, rather than in Hensel's order
+            letters.sort_unstable();
+            if letters.is_empty() {
+                // No letters for 0 and 8 neighbors
+                if outcome(if count == 0 { 0 } else { 0xff }) {
+                    write!(f, "{}", count)?;
+                }
+                continue;
+            }
+            let included: String = letters
+                .iter()
+                .filter(|(_, i)| *i)
+                .map(|(l, _)| *l)
+                .collect();
+            let excluded: String = letters
+                .iter()
+                .filter(|(_, i)| !*i)
+                .map(|(l, _)| *l)
+                .collect();
+            if excluded.is_empty() {
+                write!(f, "{}", count)?;
+            } else if included.len() <= excluded.len() {
+                if !included.is_empty() {
+                    write!(f, "{}{}", count, included)?;
+                }
+            } else {
+                write!(f, "{}-{}", count, excluded)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Sets the outcomes of one section in `table`, for cells that are `alive` or not
+fn parse_section(
+    section: &str,
+    table: &mut TransitionTable,
+    alive: bool,
+) -> Result<(), RuleParseError> {
+    let mut chars = section.chars().peekable();
+    while let Some(c) = chars.next() {
+        let count = match c.to_digit(10) {
+            Some(count) if count <= 8 => count as usize,
+            Some(_) => return Err(RuleParseError::InvalidNeighborCount(c)),
+            None if c.is_ascii_alphabetic() => return Err(RuleParseError::DigitWithoutSection(c)),
+            None => return Err(RuleParseError::UnexpectedCharacter(c)),
+        };
+        let negated = chars.next_if_eq(&'-').is_some();
+        let mut listed = vec![];
+        while let Some(letter) = chars.next_if(|c| c.is_ascii_lowercase()) {
+            let neighborhood = letters(count)
+                .find(|(l, _)| *l == letter)
+                .map(|(_, neighborhood)| neighborhood)
+                .ok_or_else(|| {
+                    RuleParseError::InvalidNeighborhoodLetter(format!("{}{}", count, letter))
+                })?;
+            listed.extend(symmetries(neighborhood));
+        }
+        if negated && listed.is_empty() {
+            return Err(RuleParseError::UnexpectedCharacter('-'));
+        }
+        for neighborhood in (0..=255u8).filter(|n| n.count_ones() as usize == count) {
+            // Without letters every arrangement is included
+            let included = listed.is_empty() || listed.contains(&neighborhood) != negated;
+            if included {
+                table.set(alive, neighborhood);
+            }
+        }
+    }
+    Ok(())
+}
+
+impl FromStr for Isotropic {
+    type Err = RuleParseError;
+
+    /// Parses "B2-a/S12" style rulestrings. Each count may be followed by the letters of the
+    /// arrangements it applies to, or by a '-' and the letters it doesn't apply to. A "/C3"
+    /// section sets the number of states.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut table = TransitionTable::default();
+        let (mut birth, mut survival, mut states) = (None, None, None);
+        for part in s.trim().split('/') {
+            let mut chars = part.chars();
+            let section = match chars.next() {
+                Some(c) => c.to_ascii_uppercase(),
+                None => return Err(RuleParseError::Empty),
+            };
+            let value = chars.as_str();
+            let duplicate = match section {
+                'B' => birth.replace(value).is_some(),
+                'S' => survival.replace(value).is_some(),
+                'C' => states.replace(value).is_some(),
+                c => return Err(RuleParseError::UnexpectedCharacter(c)),
+            };
+            if duplicate {
+                return Err(RuleParseError::DuplicateSection(section));
+            }
+        }
+        parse_section(
+            birth.ok_or(RuleParseError::MissingSection('B'))?,
+            &mut table,
+            false,
+        )?;
+        parse_section(
+            survival.ok_or(RuleParseError::MissingSection('S'))?,
+            &mut table,
+            true,
+        )?;
+        let states = match states {
+            Some(states) => match states.parse() {
+                Ok(states) if states >= 2 => states,
+                _ => return Err(RuleParseError::InvalidStateCount(states.to_string())),
+            },
+            None => 2,
+        };
+        Ok(Self { table, states })
+    }
+}
+
+impl fmt::Display for Isotropic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "B")?;
+        self.write_section(f, false)?;
+        write!(f, "/S")?;
+        self.write_section(f, true)?;
+        if self.states > 2 {
+            write!(f, "/C{}", self.states)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,18 @@
+//! Cellular automata on bounded grids, unbounded planes and HashLife, independent of the Bevy
+//! frontend that draws them
+
+pub mod bitgrid;
+pub mod executor;
+pub mod hashlife;
+pub mod isotropic;
+pub mod ltl;
+pub mod pattern;
+pub mod rule;
+pub mod ruletable;
+pub mod sparse;
+pub mod state;
+pub mod topology;
+pub mod undo;
+pub mod universe;
+
+pub use universe::Universe;
@@ -0,0 +1,250 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::rule::RuleParseError;
+
+/// The largest neighborhood radius a rule may use
+pub const MAX_RADIUS: u32 = 50;
+
+/// The shape of the cells counted around a cell
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Neighborhood {
+    /// The square of cells at most `radius` steps away along both axes
+    Moore,
+    /// The diamond of cells at most `radius` steps away when only moving along the axes
+    VonNeumann,
+    /// The cells whose center is closer than `radius + 0.5` to the center of the cell
+    Circular,
+}
+
+impl Neighborhood {
+    /// How far the neighborhood reaches to the left and right in the row `dy` rows away
+    fn row_reach(self, dy: i64, radius: i64) -> i64 {
+        match self {
+            Self::Moore => radius,
+            Self::VonNeumann => radius - dy.abs(),
+            // dx² + dy² < (r + 0.5)², which for whole numbers is dx² + dy² <= r² + r
+            Self::Circular => {
+                let limit = radius * radius + radius - dy * dy;
+                (0..=radius).rev().find(|dx| dx * dx <= limit).unwrap_or(0)
+            }
+        }
+    }
+
+    fn letter(self) -> char {
+        match self {
+            Self::Moore => 'M',
+            Self::VonNeumann => 'N',
+            Self::Circular => 'C',
+        }
+    }
+}
+
+/// A Larger than Life rule, like Bosco's Rule "R5,C0,M1,S34..58,B34..45,NM". Live cells are
+/// counted in a neighborhood of the given radius and shape, the cell itself included with
+/// `include_center`. Dead cells with a count in `birth` come alive, live cells with a count in
+/// `survival` stay alive. With more than two `states` the cells that don't survive decay like in
+/// Generations rules.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct LargerThanLife {
+    pub radius: u32,
+    pub states: u8,
+    pub include_center: bool,
+    /// The smallest and largest count, both included
+    pub survival: (u32, u32),
+    pub birth: (u32, u32),
+    pub neighborhood: Neighborhood,
+}
+
+impl LargerThanLife {
+    /// The state after `state`, for a cell with `count` live cells in its neighborhood
+    pub fn next_cell(&self, state: u8, count: u32) -> u8 {
+        let within = |(min, max): (u32, u32)| (min..=max).contains(&count);
+        match state {
+            0 => within(self.birth) as u8,
+            1 if within(self.survival) => 1,
+            _ if state + 1 < self.states => state + 1,
+            _ => 0,
+        }
+    }
+
+    /// Computes the next states of a `width` by `height` block of cells, indexed by
+    /// `x * height + y`. `state(x, y)` gives the states of the block and of the cells up to
+    /// `radius` around it.
+    ///
+    /// The live cells are summed up in a summed-area table, so counting a rectangle of cells
+    /// takes four lookups. Moore neighborhoods are a single rectangle, other shapes are summed
+    /// up one row at a time.
+    pub fn step_block(
+        &self,
+        width: usize,
+        height: usize,
+        state: impl Fn(i64, i64) -> u8,
+    ) -> Vec<u8> {
+        let radius = self.radius as i64;
+        let padded_width = width + 2 * self.radius as usize;
+        let padded_height = height + 2 * self.radius as usize;
+        let mut states = vec![0; padded_width * padded_height];
+        // sums[x * (padded_height + 1) + y] counts the live cells left of x and below y
+        let mut sums = vec![0u32; (padded_width + 1) * (padded_height + 1)];
+        let sum_index = |x: usize, y: usize| x * (padded_height + 1) + y;
+        for x in 0..padded_width {
+            for y in 0..padded_height {
+                let cell = state(x as i64 - radius, y as i64 - radius);
+                states[x * padded_height + y] = cell;
+                sums[sum_index(x + 1, y + 1)] =
+                    (cell == 1) as u32 + sums[sum_index(x, y + 1)] + sums[sum_index(x + 1, y)]
+                        - sums[sum_index(x, y)];
+            }
+        }
+        // The live cells in the rectangle from (x0, y0) to (x1, y1), all of them included
+        let count_rectangle = |x0: usize, y0: usize, x1: usize, y1: usize| {
+            sums[sum_index(x1 + 1, y1 + 1)] + sums[sum_index(x0, y0)]
+                - sums[sum_index(x0, y1 + 1)]
+                - sums[sum_index(x1 + 1, y0)]
+        };
+        let reaches: Vec<i64> = (-radius..=radius)
+            .map(|dy| self.neighborhood.row_reach(dy, radius))
+            .collect();
+
+        let mut next = vec![0; width * height];
+        for x in 0..width {
+            for y in 0..height {
+                // The cell in padded coordinates
+                let (px, py) = (x + self.radius as usize, y + self.radius as usize);
+                let cell = states[px * padded_height + py];
+                let mut count = if self.neighborhood == Neighborhood::Moore {
+                    count_rectangle(
+                        x,
+                        y,
+                        x + 2 * self.radius as usize,
+                        y + 2 * self.radius as usize,
+                    )
+                } else {
+                    reaches
+                        .iter()
+                        .enumerate()
+                        .map(|(row, reach)| {
+                            let reach = *reach as usize;
+                            count_rectangle(px - reach, y + row, px + reach, y + row)
+                        })
+                        .sum()
+                };
+                if !self.include_center && cell == 1 {
+                    count -= 1;
+                }
+                next[x * height + y] = self.next_cell(cell, count);
+            }
+        }
+        next
+    }
+}
+
+fn parse_number(item: &str, value: &str) -> Result<u32, RuleParseError> {
+    value
+        .trim()
+        .parse()
+        .map_err(|_| RuleParseError::InvalidParameter(item.to_string()))
+}
+
+fn parse_range(item: &str, value: &str) -> Result<(u32, u32), RuleParseError> {
+    let (min, max) = value
+        .split_once("..")
+        .ok_or_else(|| RuleParseError::InvalidParameter(item.to_string()))?;
+    let range = (parse_number(item, min)?, parse_number(item, max)?);
+    if range.0 > range.1 {
+        return Err(RuleParseError::InvalidParameter(item.to_string()));
+    }
+    Ok(range)
+}
+
+impl FromStr for LargerThanLife {
+    type Err = RuleParseError;
+
+    /// Parses comma separated parameters: the radius "R", the number of states "C" (0 and 1
+    /// mean two states), whether the center counts "M0" or "M1", the ranges "Smin..max" and
+    /// "Bmin..max" and the neighborhood "NM" (Moore), "NN" (von Neumann) or "NC" (circular).
+    /// "C", "M" and "N" are optional.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut radius = None;
+        let mut states = None;
+        let mut include_center = None;
+        let mut survival = None;
+        let mut birth = None;
+        let mut neighborhood = None;
+        for item in s.split(',').map(str::trim) {
+            let mut chars = item.chars();
+            let section = chars
+                .next()
+                .ok_or(RuleParseError::Empty)?
+                .to_ascii_uppercase();
+            let value = chars.as_str();
+            let invalid = || RuleParseError::InvalidParameter(item.to_string());
+            let duplicate = match section {
+                'R' => {
+                    let value = parse_number(item, value)?;
+                    if !(1..=MAX_RADIUS).contains(&value) {
+                        return Err(invalid());
+                    }
+                    radius.replace(value).is_some()
+                }
+                'C' => {
+                    let value = match parse_number(item, value)? {
+                        0..=2 => 2,
+                        value @ 3..=255 => value as u8,
+                        _ => return Err(invalid()),
+                    };
+                    states.replace(value).is_some()
+                }
+                'M' => {
+                    let value = match value {
+                        "0" => false,
+                        "1" => true,
+                        _ => return Err(invalid()),
+                    };
+                    include_center.replace(value).is_some()
+                }
+                'S' => survival.replace(parse_range(item, value)?).is_some(),
+                'B' => birth.replace(parse_range(item, value)?).is_some(),
+                'N' => {
+                    let value = match value.to_ascii_uppercase().as_str() {
+                        "M" => Neighborhood::Moore,
+                        "N" => Neighborhood::VonNeumann,
+                        "C" => Neighborhood::Circular,
+                        _ => return Err(invalid()),
+                    };
+                    neighborhood.replace(value).is_some()
+                }
+                c => return Err(RuleParseError::UnexpectedCharacter(c)),
+            };
+            if duplicate {
+                return Err(RuleParseError::DuplicateSection(section));
+            }
+        }
+        Ok(Self {
+            radius: radius.ok_or(RuleParseError::MissingSection('R'))?,
+            states: states.unwrap_or(2),
+            include_center: include_center.unwrap_or(false),
+            survival: survival.ok_or(RuleParseError::MissingSection('S'))?,
+            birth: birth.ok_or(RuleParseError::MissingSection('B'))?,
+            neighborhood: neighborhood.unwrap_or(Neighborhood::Moore),
+        })
+    }
+}
+
+impl fmt::Display for LargerThanLife {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "R{},C{},M{},S{}..{},B{}..{},N{}",
+            self.radius,
+            if self.states > 2 { self.states } else { 0 },
+            self.include_center as u8,
+            self.survival.0,
+            self.survival.1,
+            self.birth.0,
+            self.birth.1,
+            self.neighborhood.letter(),
+        )
+    }
+}
@@ -1,58 +1,166 @@
-use bevy::prelude::*;
-use bevy::ecs::schedule::ShouldRun;
+use std::env;
+use std::process;
+use std::time::{Duration, Instant};
 
-use modulo::Mod;
+use bevy::ecs::schedule::ShouldRun;
+use bevy::prelude::*;
+use bevy::tasks::{ComputeTaskPool, TaskPool};
+use game_of_life::executor::Executor;
+use game_of_life::state::{Coord, GridSize, StateGrid};
+use game_of_life::undo::History;
+use game_of_life::Universe;
 
-use crate::grid::{GridPlugin, Position, Size};
+use crate::args::Options;
+use crate::camera::{CameraPlugin, HoveredCell};
+use crate::config::{Config, ConfigFile, ConfigPlugin};
+use crate::controls::{Action, Bindings, Controls, ControlsPlugin, DIGITS};
+use crate::files::{PatternFilePlugin, PatternPath};
+use crate::grid::{
+    cell_color, BoardView, CellColors, GridPlugin, Position, RenderMode, Size, ViewOrigin,
+    VisibleCells,
+};
+use crate::history::HistoryPlugin;
+use crate::startup::{check_unbounded, Engine, Startup};
+use crate::stats::{SimulationStats, StatsPlugin};
+use crate::texture::BoardTexturePlugin;
 
+mod args;
+mod camera;
+mod config;
+mod controls;
+mod files;
 mod grid;
+mod headless;
+mod history;
+mod startup;
+mod stats;
+mod texture;
+
+/// How many cells the Up and Down keys add to or remove from each side of the grid
+const GRID_SIZE_STEP: usize = 10;
 
-pub const GRID_HEIGHT: usize = 50;
-pub const GRID_WIDTH: usize = 50;
+/// The largest number of generations a single HashLife update may skip, as a power of two. The
+/// other engines compute every generation, so they advance by one generation per update.
+const MAX_STEP_EXPONENT: u32 = 40;
 
-const NEIGHBORS: [[i32;2];8] = [
-    [-1,-1],[0,-1],[1,-1],
-    [-1,0],[1,0],
-    [-1,1],[0,1],[1,1],
-];
+/// How long a frame keeps computing the generations of a step command before drawing them
+const PENDING_STEP_BUDGET: Duration = Duration::from_millis(20);
+
+/// The window size in pixels, unless the command line sets it
+const DEFAULT_WINDOW_SIZE: (f32, f32) = (500.0, 500.0);
 
 fn main() {
+    let options = match Options::parse(env::args().skip(1)) {
+        Ok(options) => options,
+        Err(err) => {
+            eprintln!("{}\n\n{}", err, args::USAGE);
+            process::exit(2);
+        }
+    };
+    if options.help {
+        println!("{}", args::USAGE);
+        return;
+    }
+    if options.headless {
+        if let Err(err) = headless::run(&options) {
+            eprintln!("Error: {}", err);
+            process::exit(1);
+        }
+        return;
+    }
+    let config_path = Config::path();
+    let saved_config = match config_path.as_deref().map(Config::load).transpose() {
+        Ok(config) => config.unwrap_or_default(),
+        Err(err) => {
+            let path = config_path.unwrap_or_default();
+            eprintln!("Error: the config file {} {}", path.display(), err);
+            process::exit(1);
+        }
+    };
+    let startup = match Startup::new(&options, &saved_config, Engine::Bounded) {
+        Ok(startup) => startup,
+        Err(err) => {
+            eprintln!("Error: {}", err);
+            process::exit(1);
+        }
+    };
+    let grid_size = startup.grid_size;
+    let mut config = saved_config.clone();
+    if options.pattern.is_some() {
+        config.pattern = options.pattern.clone();
+    }
+    let pattern_path = config.pattern.clone().map(PatternPath).unwrap_or_default();
+    let bindings = Bindings::new(&config.keys).expect("the config file was validated");
+    let (width, height) = options.window.unwrap_or(DEFAULT_WINDOW_SIZE);
     App::new()
-        .init_resource::<StateGrid>()
-        .insert_resource(Speed(0.1))
+        .insert_resource(grid_size)
+        .insert_resource(startup.universe)
+        .insert_resource(startup.view_origin)
+        .insert_resource(VisibleCells::board(startup.view_origin, grid_size))
+        .insert_resource(RenderMode::for_size(grid_size))
+        .insert_resource(pattern_path)
+        .insert_resource(Speed(options.speed.unwrap_or(config.speed)))
+        .insert_resource(History::new(
+            options
+                .history_memory
+                .unwrap_or(config.history_memory)
+                .saturating_mul(1 << 20),
+        ))
+        .insert_resource(CellColors::from(config.colors))
+        .insert_resource(bindings)
+        .insert_resource(config)
+        .insert_resource(ConfigFile {
+            path: config_path,
+            saved: saved_config,
+        })
+        .insert_resource(StepExponent(0))
+        .insert_resource(PendingGenerations(0))
+        .insert_resource(NumberInput(None))
         .insert_resource(EntityGrid(vec![]))
-        .insert_resource(Paused(true))
-        .insert_resource(LastMouseCell(-1,-1))
+        .insert_resource(Paused(!options.run))
+        .insert_resource(LastMouseCell(-1, -1))
+        .insert_resource(DrawState(1))
         .insert_resource(WindowDescriptor {
-            width: 500.0,
-            height: 500.0,
+            width,
+            height,
             ..default()
         })
-        .add_startup_system(setup_camera)
-        .add_startup_system(spawn_grid)
+        .add_startup_system(use_compute_task_pool)
         .add_system_set(
             SystemSet::new()
                 .with_run_criteria(should_update_run)
                 .with_system(update_cells),
         )
+        .add_system(spawn_grid.after("render_mode"))
         .add_system(spawn_cells_with_mouse)
+        .add_system(select_draw_state)
         .add_system(handle_keyboard_input)
+        .add_system(handle_step_commands)
+        .add_system(cycle_topology)
         .add_system_to_stage(CoreStage::PostUpdate, update_cell_sprites)
         .add_plugin(GridPlugin)
+        .add_plugin(CameraPlugin)
+        .add_plugin(PatternFilePlugin)
+        .add_plugin(ConfigPlugin)
+        .add_plugin(ControlsPlugin)
+        .add_plugin(StatsPlugin)
+        .add_plugin(HistoryPlugin)
+        .add_plugin(BoardTexturePlugin)
         .add_plugins(DefaultPlugins)
         .run()
 }
 
+struct Speed(f32);
 
-struct StateGrid([[bool; GRID_HEIGHT]; GRID_WIDTH]);
+/// Each update advances the simulation by 2^n generations
+struct StepExponent(u32);
 
-impl Default for StateGrid {
-    fn default() -> Self{
-        Self([[false; GRID_HEIGHT]; GRID_WIDTH])
-    }
-}
+/// Generations left to compute for a step command. Every frame computes as many of them as fit in
+/// `PENDING_STEP_BUDGET`, without waiting for the timer.
+struct PendingGenerations(u64);
 
-struct Speed(f32);
+/// The number typed in for the next step command
+struct NumberInput(Option<u64>);
 
 struct EntityGrid(Vec<Vec<Entity>>);
 
@@ -60,20 +168,77 @@ struct Paused(bool);
 
 struct LastMouseCell(i32, i32);
 
+/// The state the mouse draws, for rules with more than one state that isn't dead
+struct DrawState(u8);
+
 #[derive(Component)]
 struct Cell;
 
-fn setup_camera(mut commands: Commands) {
-    commands.spawn_bundle(OrthographicCameraBundle::new_2d());
+/// Runs the bands of parallel steps on Bevy's compute task pool
+struct ComputePool(TaskPool);
+
+impl Executor for ComputePool {
+    fn threads(&self) -> usize {
+        self.0.thread_num()
+    }
+
+    fn run<'a>(&self, tasks: Vec<Box<dyn FnOnce() + Send + 'a>>) {
+        self.0.scope(|scope| {
+            for task in tasks {
+                scope.spawn(async move { task() });
+            }
+        });
+    }
 }
 
+fn use_compute_task_pool(pool: Res<ComputeTaskPool>, mut universe: ResMut<Universe>) {
+    universe.set_executor(Box::new(ComputePool(pool.0.clone())));
+}
+
+/// (Re)spawns the cell sprites whenever the number of visible cells or the render mode changes,
+/// including on the first frame, and moves them along with the visible cells otherwise
 fn spawn_grid(
     mut commands: Commands,
+    view: BoardView,
+    render_mode: Res<RenderMode>,
+    mut universe: ResMut<Universe>,
     mut grid: ResMut<EntityGrid>,
-    ) {
-    for x in 0..GRID_WIDTH {
+    mut history: ResMut<History>,
+    mut positions: Query<&mut Position, With<Cell>>,
+) {
+    if view.grid_size.is_changed() {
+        // Cells outside of the new size are lost, so older changes might not apply anymore. Loads
+        // and undoing them bring their own grid of the new size.
+        if let StateGrid::Bounded(grid) = universe.grid() {
+            if grid.size() != *view.grid_size {
+                history.clear();
+            }
+        }
+        universe.resize(*view.grid_size);
+    }
+    let (visible, origin) = (*view.visible, *view.origin);
+    let size = visible.size();
+    let spawned =
+        grid.0.len() == size.width && grid.0.iter().all(|column| column.len() == size.height);
+    if !render_mode.is_changed() && spawned {
+        if view.visible.is_changed() || view.origin.is_changed() {
+            for (x, column) in grid.0.iter().enumerate() {
+                for (y, id) in column.iter().enumerate() {
+                    *positions.get_mut(*id).unwrap() = visible.position(origin, x, y);
+                }
+            }
+        }
+        return;
+    }
+    for id in grid.0.drain(..).flatten() {
+        commands.entity(id).despawn();
+    }
+    if *render_mode != RenderMode::Sprites {
+        return;
+    }
+    for x in 0..size.width {
         (*grid).0.push(vec![]);
-        for y in 0..GRID_HEIGHT {
+        for y in 0..size.height {
             let id = commands
                 .spawn_bundle(SpriteBundle {
                     sprite: Sprite {
@@ -82,8 +247,8 @@ fn spawn_grid(
                     },
                     ..default()
                 })
-            .insert(Cell)
-                .insert(Position { x: x as i32, y: y as i32})
+                .insert(Cell)
+                .insert(visible.position(origin, x, y))
                 .insert(Size::square(0.8))
                 .id();
             (*grid).0[x].push(id);
@@ -92,97 +257,248 @@ fn spawn_grid(
 }
 
 fn update_cells(
-    mut state_grid: ResMut<StateGrid>,
-    ) {
-    let initial_state_grid = (*state_grid).0.clone();
-    for x in 0..GRID_WIDTH {
-        for y in 0..GRID_HEIGHT { 
-            let mut num_alive_nb = 0;
-            for [dx,dy] in NEIGHBORS {
-                let nx = (dx + x as i32).modulo(GRID_WIDTH as i32) as usize;
-                let ny = (dy + y as i32).modulo(GRID_HEIGHT as i32) as usize;
-                if initial_state_grid[nx][ny] {
-                    num_alive_nb += 1;
-                }
-            }
-            let mut alive = initial_state_grid[x][y];
-            if alive && num_alive_nb < 2 {
-                alive = false;
-            } else if alive && num_alive_nb > 3 {
-                alive = false;
-            } else if !alive && num_alive_nb == 3 {
-                alive = true;
-            }
-            (*state_grid).0[x][y] = alive;
+    mut universe: ResMut<Universe>,
+    step_exponent: Res<StepExponent>,
+    mut pending: ResMut<PendingGenerations>,
+    mut stats: ResMut<SimulationStats>,
+    mut history: ResMut<History>,
+) {
+    let start = Instant::now();
+    loop {
+        let mut step_log2 = step_exponent.0;
+        if pending.0 > 0 {
+            // Never overshoot the generations a step command asked for
+            step_log2 = step_log2.min(pending.0.ilog2());
+            pending.0 -= 1 << step_log2;
+        }
+        // One copy of the cells serves both the births and deaths and the cells the step
+        // flipped, which undo keeps
+        let counted = SimulationStats::counts_step(universe.grid());
+        let recorded = history.records_step(universe.grid());
+        let cells_before = (counted || recorded).then(|| universe.grid().cells());
+        let generation_before = universe.generation();
+        universe.step_pow2(step_log2);
+        stats.record_step(cells_before.as_deref().filter(|_| counted), universe.grid());
+        if let Some(cells) = cells_before.as_deref().filter(|_| recorded) {
+            history.record_step(cells, generation_before, universe.grid());
+        }
+        // The timer runs one step per update, step commands go on for the rest of the budget
+        if pending.0 == 0 || start.elapsed() >= PENDING_STEP_BUDGET {
+            break;
         }
     }
 }
 
 fn update_cell_sprites(
-    state_grid: Res<StateGrid>,
+    universe: Res<Universe>,
     entity_grid: Res<EntityGrid>,
+    visible: Res<VisibleCells>,
+    colors: Res<CellColors>,
     mut sprites: Query<&mut Sprite, With<Cell>>,
-    ) {
-    for x in 0..GRID_WIDTH {
-        for y in 0..GRID_HEIGHT {
-            let mut sprite = sprites.get_mut((*entity_grid).0[x][y]).unwrap();
-            sprite.color = if (*state_grid).0[x][y] { Color::WHITE } else { Color::BLACK };
+) {
+    let min = visible.0.min;
+    for (x, column) in entity_grid.0.iter().enumerate() {
+        for (y, id) in column.iter().enumerate() {
+            let mut sprite = sprites.get_mut(*id).unwrap();
+            let pos = Coord::new(min.x + x as i64, min.y + y as i64);
+            sprite.color = cell_color(universe.get(pos), universe.rule(), &colors);
         }
     }
 }
 
-
 fn should_update_run(
     paused: Res<Paused>,
     time: Res<Time>,
     mut next_run_time: Local<u128>,
     speed: Res<Speed>,
-    ) -> ShouldRun {
-        let milis = time.time_since_startup().as_millis();
-        if !paused.0 && milis > *next_run_time {
-            *next_run_time = milis + (speed.0 * 1000.0) as u128;
-            return ShouldRun::Yes;
-        }
-        ShouldRun::No
+    pending: Res<PendingGenerations>,
+) -> ShouldRun {
+    if pending.0 > 0 {
+        return ShouldRun::Yes;
+    }
+    let milis = time.time_since_startup().as_millis();
+    if !paused.0 && milis > *next_run_time {
+        *next_run_time = milis + (speed.0 * 1000.0) as u128;
+        return ShouldRun::Yes;
+    }
+    ShouldRun::No
 }
 
+/// Drawing toggles the cells under the mouse between dead and the draw state, erasing kills them.
+/// Cells changed in one stroke are undone together.
 fn spawn_cells_with_mouse(
-    windows: Res<Windows>,
-    buttons: Res<Input<MouseButton>>,
-    mut state_grid: ResMut<StateGrid>,
+    controls: Controls,
+    hovered: Res<HoveredCell>,
+    view_origin: Res<ViewOrigin>,
+    draw_state: Res<DrawState>,
+    mut universe: ResMut<Universe>,
     mut last_cell: ResMut<LastMouseCell>,
-    ) {
-    if buttons.pressed(MouseButton::Left) {
-        let window = windows.get_primary().unwrap();
-        if let Some(position) = window.cursor_position() {
-            let x = position.x as f32 / window.width() as f32 * GRID_WIDTH as f32;
-            let y = position.y as f32 / window.height() as f32 * GRID_HEIGHT as f32;
-            if last_cell.0 != x as i32 || last_cell.1 != y as i32 {
-                (*state_grid).0[x as usize][y as usize] = !state_grid.0[x as usize][y as usize];
-                (*last_cell).0 = x as i32;
-                (*last_cell).1 = y as i32;
+    mut history: ResMut<History>,
+) {
+    let draw = controls.pressed(Action::Draw);
+    if !draw && !controls.pressed(Action::Erase) {
+        if last_cell.0 != -1 || last_cell.1 != -1 {
+            history.end_stroke();
+            *last_cell = LastMouseCell(-1, -1);
+        }
+        return;
+    }
+    if let Some(cell) = hovered.0 {
+        if last_cell.0 != cell.x || last_cell.1 != cell.y {
+            let pos = Coord::new(
+                view_origin.0.x + cell.x as i64,
+                view_origin.0.y + cell.y as i64,
+            );
+            let state_before = universe.get(pos);
+            if draw {
+                universe.toggle(pos, draw_state.0);
+            } else if state_before != 0 {
+                universe.set(pos, 0);
             }
+            if universe.get(pos) != state_before {
+                history.record_cell(pos, state_before, universe.get(pos), universe.generation());
+            }
+            (*last_cell).0 = cell.x;
+            (*last_cell).1 = cell.y;
         }
     }
 }
 
+/// Picks the previous or next state to draw. Switching to a rule with fewer states goes back to
+/// drawing live cells.
+fn select_draw_state(
+    controls: Controls,
+    universe: Res<Universe>,
+    mut draw_state: ResMut<DrawState>,
+) {
+    // At most 255, the highest state a cell can store
+    let last = (universe.rule().states() - 1) as u8;
+    let mut state = draw_state.0.min(last);
+    if controls.just_released(Action::PreviousState) {
+        state = if state > 1 { state - 1 } else { last };
+    }
+    if controls.just_released(Action::NextState) {
+        state = if state < last { state + 1 } else { 1 };
+    }
+    if state != draw_state.0 {
+        draw_state.0 = state;
+        info!("Drawing state {}", state);
+    }
+}
+
 fn handle_keyboard_input(
-    keyboard_input: Res<Input<KeyCode>>,
+    controls: Controls,
     mut speed: ResMut<Speed>,
+    mut step_exponent: ResMut<StepExponent>,
     mut paused: ResMut<Paused>,
-    mut commands: Commands,
-    ) {
-        if keyboard_input.just_released(KeyCode::Space) {
-            (*paused).0 = !paused.0;
-        }
-        if keyboard_input.just_released(KeyCode::Left) {
-            (*speed).0 = speed.0 * 1.25;
+    mut grid_size: ResMut<GridSize>,
+    mut universe: ResMut<Universe>,
+    mut history: ResMut<History>,
+) {
+    if controls.just_released(Action::TogglePause) {
+        (*paused).0 = !paused.0;
+        history.end_run();
+    }
+    if controls.just_released(Action::SlowDown) {
+        (*speed).0 = speed.0 * 1.25;
+    }
+    if controls.just_released(Action::SpeedUp) {
+        (*speed).0 = speed.0 * 0.75;
+    }
+    if controls.just_released(Action::Clear) {
+        let cells_before = History::snapshot(universe.grid());
+        let generation_before = universe.generation();
+        universe.clear();
+        history.record(&cells_before, generation_before, universe.grid());
+        (*paused).0 = true;
+    }
+    if controls.just_released(Action::NextEngine) {
+        match check_unbounded(universe.rule()) {
+            Ok(()) => {
+                universe.next_engine(*grid_size);
+                history.clear();
+            }
+            Err(err) => info!("{}", err),
         }
-        if keyboard_input.just_released(KeyCode::Right) {
-            (*speed).0 = speed.0 * 0.75;
+    }
+    let hashlife = matches!(universe.grid(), StateGrid::HashLife(_));
+    if controls.just_released(Action::MoreGenerations) {
+        if !hashlife {
+            info!("Only HashLife skips generations");
+        } else if step_exponent.0 < MAX_STEP_EXPONENT {
+            step_exponent.0 += 1;
         }
-        if keyboard_input.just_released(KeyCode::C) {
-            commands.insert_resource(StateGrid::default());
-            (*paused).0 = true;
+    }
+    if controls.just_released(Action::FewerGenerations) && step_exponent.0 > 0 {
+        step_exponent.0 -= 1;
+    }
+    // Loading a pattern or a rule HashLife can't run may switch engines as well
+    if !hashlife && step_exponent.0 > 0 {
+        step_exponent.0 = 0;
+    }
+    if controls.just_released(Action::GrowGrid) {
+        grid_size.width += GRID_SIZE_STEP;
+        grid_size.height += GRID_SIZE_STEP;
+    }
+    if controls.just_released(Action::ShrinkGrid)
+        && grid_size.width > GRID_SIZE_STEP
+        && grid_size.height > GRID_SIZE_STEP
+    {
+        grid_size.width -= GRID_SIZE_STEP;
+        grid_size.height -= GRID_SIZE_STEP;
+    }
+}
+
+/// Steps a single generation, or the typed number of generations, or runs until the typed
+/// generation
+fn handle_step_commands(
+    controls: Controls,
+    universe: Res<Universe>,
+    mut number_input: ResMut<NumberInput>,
+    mut pending: ResMut<PendingGenerations>,
+    mut paused: ResMut<Paused>,
+    mut history: ResMut<History>,
+) {
+    for key in controls.released_keys() {
+        if let Some(digit) = digit_value(*key) {
+            let number = number_input.0.unwrap_or(0);
+            number_input.0 = Some(number.saturating_mul(10).saturating_add(digit));
         }
+    }
+    if controls.just_released(Action::DiscardNumber) {
+        number_input.0 = None;
+    }
+    let steps = if controls.just_released(Action::Step) {
+        Some(1)
+    } else if controls.just_released(Action::StepNumber) {
+        Some(number_input.0.take().unwrap_or(1))
+    } else if controls.just_released(Action::RunToNumber) {
+        number_input
+            .0
+            .take()
+            .map(|target| target.saturating_sub(universe.generation()))
+    } else {
+        None
+    };
+    if let Some(steps) = steps {
+        pending.0 = steps;
+        (*paused).0 = true;
+        history.end_run();
+    }
+}
+
+/// Cycles through the topologies of the bounded grid
+fn cycle_topology(controls: Controls, mut universe: ResMut<Universe>) {
+    if controls.just_released(Action::NextTopology) {
+        let topology = universe.topology().next();
+        universe.set_topology(topology);
+        info!("Switched to a {}", topology);
+    }
+}
+
+fn digit_value(key: KeyCode) -> Option<u64> {
+    DIGITS
+        .iter()
+        .position(|(key_code, numpad)| key == *key_code || key == *numpad)
+        .map(|digit| digit as u64)
 }
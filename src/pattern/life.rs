@@ -0,0 +1,138 @@
+//! The Life 1.05 and Life 1.06 formats, see <https://conwaylife.com/wiki/Life_1.05> and
+//! <https://conwaylife.com/wiki/Life_1.06>
+
+use std::fmt::Write;
+
+use crate::pattern::{Pattern, PatternError};
+use crate::rule::Rule;
+use crate::state::Coord;
+use crate::topology::split_rulestring;
+
+const HEADER_105: &str = "#Life 1.05";
+const HEADER_106: &str = "#Life 1.06";
+
+/// Coordinates further than this from the origin are rejected, so the pattern fits into every
+/// engine after being moved to its place on the board
+const MAX_COORDINATE: i64 = 1 << 60;
+
+/// Reads either version, depending on the header
+pub fn read(input: &str) -> Result<Pattern, PatternError> {
+    let mut lines = input
+        .lines()
+        .enumerate()
+        .map(|(i, line)| (i + 1, line.trim()));
+    let mut pattern = match lines.next() {
+        Some((_, HEADER_105)) => read_105(lines)?,
+        Some((_, HEADER_106)) => read_106(lines)?,
+        _ => return Err(PatternError::MissingHeader(HEADER_106)),
+    };
+    pattern.normalize();
+    Ok(pattern)
+}
+
+fn parse_coordinate(value: Option<&str>, line: usize) -> Result<i64, PatternError> {
+    let value: i128 = value
+        .and_then(|value| value.parse().ok())
+        .ok_or(PatternError::InvalidCoordinates { line })?;
+    if value.abs() > MAX_COORDINATE as i128 {
+        return Err(PatternError::CoordinateOutOfRange { line });
+    }
+    Ok(value as i64)
+}
+
+fn read_106<'a>(lines: impl Iterator<Item = (usize, &'a str)>) -> Result<Pattern, PatternError> {
+    let mut pattern = Pattern::default();
+    for (line, text) in lines {
+        if text.is_empty() || text.starts_with('#') {
+            continue;
+        }
+        let mut values = text.split_whitespace();
+        let x = parse_coordinate(values.next(), line)?;
+        let y = parse_coordinate(values.next(), line)?;
+        if values.next().is_some() {
+            return Err(PatternError::InvalidCoordinates { line });
+        }
+        pattern.cells.push((Coord::new(x, y), 1));
+    }
+    pattern.cells.sort_unstable();
+    pattern.cells.dedup();
+    Ok(pattern)
+}
+
+fn read_105<'a>(lines: impl Iterator<Item = (usize, &'a str)>) -> Result<Pattern, PatternError> {
+    let mut pattern = Pattern::default();
+    // The top left corner of the current block and the row within it
+    let mut block: Option<(i64, i64)> = None;
+    let mut row = 0;
+    for (line, text) in lines {
+        if let Some(description) = text.strip_prefix("#D") {
+            pattern.comments.push(description.trim().to_string());
+        } else if text.starts_with("#N") {
+            pattern.rule = Some(Rule::default().to_string());
+        } else if let Some(rule) = text.strip_prefix("#R") {
+            pattern.rule = Some(rule.trim().to_string());
+        } else if let Some(position) = text.strip_prefix("#P") {
+            let mut values = position.split_whitespace();
+            let x = parse_coordinate(values.next(), line)?;
+            let y = parse_coordinate(values.next(), line)?;
+            block = Some((x, y));
+            row = 0;
+        } else if text.starts_with('#') || text.is_empty() {
+            continue;
+        } else {
+            let (left, top) = block.ok_or(PatternError::MissingBlockPosition { line })?;
+            for (x, c) in text.chars().enumerate() {
+                match c {
+                    '.' => (),
+                    '*' => pattern
+                        .cells
+                        .push((Coord::new(left + x as i64, top + row), 1)),
+                    _ => return Err(PatternError::UnexpectedCharacter { line, character: c }),
+                }
+            }
+            row += 1;
+        }
+    }
+    pattern.cells.sort_unstable();
+    pattern.cells.dedup();
+    Ok(pattern)
+}
+
+pub fn write_106(pattern: &Pattern) -> Result<String, PatternError> {
+    pattern.check_two_states()?;
+    let mut out = format!("{}\n", HEADER_106);
+    for (pos, _) in &pattern.cells {
+        writeln!(out, "{} {}", pos.x, pos.y).unwrap();
+    }
+    Ok(out)
+}
+
+/// Writes the pattern as a single block. Rules that can't be parsed or written in the
+/// survival/birth notation are left out.
+pub fn write_105(pattern: &Pattern) -> Result<String, PatternError> {
+    pattern.check_two_states()?;
+    let mut out = format!("{}\n", HEADER_105);
+    for comment in pattern.name.iter().chain(&pattern.comments) {
+        writeln!(out, "#D {}", comment).unwrap();
+    }
+    // Life 1.05 has no bounded grids, so those are left out as well
+    let rule = pattern
+        .rule
+        .as_ref()
+        .map(|rulestring| split_rulestring(rulestring).0);
+    match rule.and_then(|rule| rule.parse::<Rule>().ok()) {
+        Some(rule) if rule == Rule::default() => out.push_str("#N\n"),
+        Some(Rule::LifeLike(rule)) => writeln!(out, "#R {}", rule.sb_notation()).unwrap(),
+        _ => (),
+    }
+    out.push_str("#P 0 0\n");
+    let mut rows = vec![vec!['.'; pattern.width as usize]; pattern.height as usize];
+    for (pos, _) in &pattern.cells {
+        rows[pos.y as usize][pos.x as usize] = '*';
+    }
+    for row in rows {
+        out.extend(row);
+        out.push('\n');
+    }
+    Ok(out)
+}
@@ -0,0 +1,202 @@
+//! Golly's macrocell format, which stores the HashLife quadtree with shared subtrees written only
+//! once, see <https://conwaylife.com/wiki/Macrocell>
+
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use crate::hashlife::{HashLife, NodeId};
+use crate::pattern::PatternError;
+
+const HEADER: &str = "[M2]";
+
+/// The level of the 8x8 leaves written as rows of cells
+const LEAF_LEVEL: u8 = 3;
+
+/// The contents of a macrocell file
+pub struct Macrocell {
+    pub universe: HashLife,
+    pub rule: Option<String>,
+}
+
+pub fn read(input: &str) -> Result<Macrocell, PatternError> {
+    let mut lines = input
+        .lines()
+        .enumerate()
+        .map(|(i, line)| (i + 1, line.trim()));
+    match lines.next() {
+        Some((_, line)) if line.starts_with(HEADER) => (),
+        _ => return Err(PatternError::MissingHeader(HEADER)),
+    }
+
+    let mut universe = HashLife::default();
+    let mut rule = None;
+    // Node 0 is the empty node of whatever level it is used at
+    let mut nodes: Vec<NodeId> = vec![];
+    for (line, text) in lines {
+        if let Some(text) = text.strip_prefix("#R") {
+            rule = Some(text.trim().to_string());
+        } else if let Some(text) = text.strip_prefix("#G") {
+            let generation = text
+                .trim()
+                .parse()
+                .map_err(|_| PatternError::InvalidNode { line })?;
+            universe.set_generation(generation);
+        } else if text.starts_with('#') || text.is_empty() {
+            continue;
+        } else if text.starts_with(['.', '*', '$']) {
+            nodes.push(read_leaf(&mut universe, text, line)?);
+        } else {
+            nodes.push(read_node(&mut universe, &nodes, text, line)?);
+        }
+    }
+    if let Some(root) = nodes.last() {
+        universe.set_root(*root);
+    }
+    Ok(Macrocell { universe, rule })
+}
+
+/// Reads an 8x8 leaf given as rows of `.` and `*`, each ended by a `$`
+fn read_leaf(universe: &mut HashLife, text: &str, line: usize) -> Result<NodeId, PatternError> {
+    let mut cells = [[false; 8]; 8];
+    let (mut x, mut y) = (0, 0);
+    for c in text.chars() {
+        match c {
+            '$' => {
+                x = 0;
+                y += 1;
+                continue;
+            }
+            '.' | '*' if x < 8 && y < 8 => cells[y][x] = c == '*',
+            _ => return Err(PatternError::UnexpectedCharacter { line, character: c }),
+        }
+        x += 1;
+    }
+    Ok(build_leaf(universe, &cells, 0, 0, LEAF_LEVEL))
+}
+
+/// Builds the node of the given level whose top left cell is at `x`, `y` in `cells`
+fn build_leaf(
+    universe: &mut HashLife,
+    cells: &[[bool; 8]; 8],
+    x: usize,
+    y: usize,
+    level: u8,
+) -> NodeId {
+    if level == 0 {
+        return HashLife::leaf(cells[y][x]);
+    }
+    let half = 1 << (level - 1);
+    let children = [
+        build_leaf(universe, cells, x, y, level - 1),
+        build_leaf(universe, cells, x + half, y, level - 1),
+        build_leaf(universe, cells, x, y + half, level - 1),
+        build_leaf(universe, cells, x + half, y + half, level - 1),
+    ];
+    universe.join(children)
+}
+
+/// Reads a `level nw ne sw se` line. Level 1 nodes list cell states instead of other nodes.
+fn read_node(
+    universe: &mut HashLife,
+    nodes: &[NodeId],
+    text: &str,
+    line: usize,
+) -> Result<NodeId, PatternError> {
+    let invalid = || PatternError::InvalidNode { line };
+    let values = text
+        .split_whitespace()
+        .map(|value| value.parse::<usize>().map_err(|_| invalid()))
+        .collect::<Result<Vec<_>, _>>()?;
+    let (level, children) = match values[..] {
+        [level, nw, ne, sw, se] if (1..=62).contains(&level) => (level as u8, [nw, ne, sw, se]),
+        _ => return Err(invalid()),
+    };
+    let mut ids = [0; 4];
+    for (id, child) in ids.iter_mut().zip(children) {
+        *id = if level == 1 {
+            HashLife::leaf(child != 0)
+        } else if child == 0 {
+            universe.empty(level - 1)
+        } else {
+            let id = *nodes
+                .get(child - 1)
+                .ok_or(PatternError::UnknownNode { line, node: child })?;
+            if universe.level(id) != level - 1 {
+                return Err(invalid());
+            }
+            id
+        };
+    }
+    Ok(universe.join(ids))
+}
+
+pub fn write(universe: &HashLife, rule: &str) -> String {
+    let mut out = format!("{} (game_of_life)\n#R {}\n", HEADER, rule);
+    if universe.generation() > 0 {
+        writeln!(out, "#G {}", universe.generation()).unwrap();
+    }
+    let mut written = HashMap::new();
+    write_node(universe, universe.root(), &mut written, &mut out);
+    out
+}
+
+/// Writes the node after its children, returning its number in the file or 0 if it is empty
+fn write_node(
+    universe: &HashLife,
+    id: NodeId,
+    written: &mut HashMap<NodeId, usize>,
+    out: &mut String,
+) -> usize {
+    if universe.node_population(id) == 0 {
+        return 0;
+    }
+    if let Some(number) = written.get(&id) {
+        return *number;
+    }
+    if universe.level(id) == LEAF_LEVEL {
+        let mut cells = [[false; 8]; 8];
+        collect_leaf(universe, id, 0, 0, &mut cells);
+        let rows: Vec<String> = cells
+            .iter()
+            .map(|row| {
+                let row: String = row
+                    .iter()
+                    .map(|alive| if *alive { '*' } else { '.' })
+                    .collect();
+                format!("{}$", row.trim_end_matches('.'))
+            })
+            .collect();
+        out.push_str(rows.concat().trim_end_matches('$'));
+        out.push_str("$\n");
+    } else {
+        let children = universe
+            .children(id)
+            .map(|child| write_node(universe, child, written, out));
+        writeln!(
+            out,
+            "{} {} {} {} {}",
+            universe.level(id),
+            children[0],
+            children[1],
+            children[2],
+            children[3]
+        )
+        .unwrap();
+    }
+    let number = written.len() + 1;
+    written.insert(id, number);
+    number
+}
+
+fn collect_leaf(universe: &HashLife, id: NodeId, x: usize, y: usize, cells: &mut [[bool; 8]; 8]) {
+    if universe.level(id) == 0 {
+        cells[y][x] = universe.node_population(id) > 0;
+        return;
+    }
+    let half = 1 << (universe.level(id) - 1);
+    let [nw, ne, sw, se] = universe.children(id);
+    collect_leaf(universe, nw, x, y, cells);
+    collect_leaf(universe, ne, x + half, y, cells);
+    collect_leaf(universe, sw, x, y + half, cells);
+    collect_leaf(universe, se, x + half, y + half, cells);
+}
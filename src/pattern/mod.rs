@@ -0,0 +1,241 @@
+use std::fmt;
+use std::path::Path;
+
+use crate::state::Coord;
+
+pub mod life;
+pub mod macrocell;
+pub mod plaintext;
+pub mod rle;
+
+/// The supported pattern file formats
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Format {
+    Rle,
+    Plaintext,
+    Life105,
+    Life106,
+}
+
+impl Format {
+    /// Picks the format from the file extension. Both Life versions are read from either
+    /// extension, `.lif` files are written as Life 1.06 and `.life` files as Life 1.05.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()?.to_ascii_lowercase().as_str() {
+            "rle" => Some(Self::Rle),
+            "cells" => Some(Self::Plaintext),
+            "lif" => Some(Self::Life106),
+            "life" => Some(Self::Life105),
+            _ => None,
+        }
+    }
+
+    pub fn read(self, input: &str) -> Result<Pattern, PatternError> {
+        match self {
+            Self::Rle => rle::read(input),
+            Self::Plaintext => plaintext::read(input),
+            Self::Life105 | Self::Life106 => life::read(input),
+        }
+    }
+
+    /// Fails for patterns with states above 1 in formats that only have live and dead cells
+    pub fn write(self, pattern: &Pattern) -> Result<String, PatternError> {
+        match self {
+            Self::Rle => Ok(rle::write(pattern)),
+            Self::Plaintext => plaintext::write(pattern),
+            Self::Life105 => life::write_105(pattern),
+            Self::Life106 => life::write_106(pattern),
+        }
+    }
+}
+
+/// A pattern as read from or written to a file. Cell coordinates follow the file layout: `x`
+/// grows to the right and `y` grows downwards from the top left corner at (0, 0).
+#[derive(Default, Clone, Debug, PartialEq, Eq)]
+pub struct Pattern {
+    pub width: u64,
+    pub height: u64,
+    /// The live cells and their states, which are always above 0
+    pub cells: Vec<(Coord, u8)>,
+    pub rule: Option<String>,
+    pub name: Option<String>,
+    pub author: Option<String>,
+    pub comments: Vec<String>,
+}
+
+impl Pattern {
+    /// Creates a pattern from live cells and their states in world coordinates, where `y` grows
+    /// upwards
+    pub fn from_cells(cells: &[(Coord, u8)]) -> Self {
+        let mut pattern = Self::default();
+        if cells.is_empty() {
+            return pattern;
+        }
+        let min_x = cells.iter().map(|(pos, _)| pos.x).min().unwrap();
+        let max_x = cells.iter().map(|(pos, _)| pos.x).max().unwrap();
+        let min_y = cells.iter().map(|(pos, _)| pos.y).min().unwrap();
+        let max_y = cells.iter().map(|(pos, _)| pos.y).max().unwrap();
+        pattern.width = (max_x - min_x + 1) as u64;
+        pattern.height = (max_y - min_y + 1) as u64;
+        pattern.cells = cells
+            .iter()
+            .map(|(pos, state)| (Coord::new(pos.x - min_x, max_y - pos.y), *state))
+            .collect();
+        pattern
+    }
+
+    /// Moves the cells so the top left corner of their bounding box is at (0, 0) and updates the
+    /// size to match
+    pub fn normalize(&mut self) {
+        let min_x = self.cells.iter().map(|(pos, _)| pos.x).min().unwrap_or(0);
+        let min_y = self.cells.iter().map(|(pos, _)| pos.y).min().unwrap_or(0);
+        for (pos, _) in &mut self.cells {
+            pos.x -= min_x;
+            pos.y -= min_y;
+        }
+        self.width = self
+            .cells
+            .iter()
+            .map(|(pos, _)| pos.x as u64 + 1)
+            .max()
+            .unwrap_or(0);
+        self.height = self
+            .cells
+            .iter()
+            .map(|(pos, _)| pos.y as u64 + 1)
+            .max()
+            .unwrap_or(0);
+    }
+
+    /// The live cells and their states in world coordinates, placed so that the pattern is
+    /// centered on `center`
+    pub fn cells_around(&self, center: Coord) -> impl Iterator<Item = (Coord, u8)> + '_ {
+        let left = center.x - self.width as i64 / 2;
+        let top = center.y + self.height as i64 / 2;
+        self.cells
+            .iter()
+            .map(move |(pos, state)| (Coord::new(left + pos.x, top - pos.y), *state))
+    }
+
+    /// Checks that every cell is in state 1, for the formats without other states
+    fn check_two_states(&self) -> Result<(), PatternError> {
+        match self.cells.iter().find(|(_, state)| *state != 1) {
+            Some((_, state)) => Err(PatternError::UnsupportedState(*state)),
+            None => Ok(()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PatternError {
+    /// The expected header is given for formats with a fixed one
+    MissingHeader(&'static str),
+    InvalidHeader(String),
+    /// A state the format can't write, like the dying states of Generations rules
+    UnsupportedState(u8),
+    UnexpectedCharacter {
+        line: usize,
+        character: char,
+    },
+    UnterminatedState {
+        line: usize,
+    },
+    InvalidState {
+        line: usize,
+        state: u16,
+    },
+    RunTooLong {
+        line: usize,
+    },
+    InvalidCoordinates {
+        line: usize,
+    },
+    CoordinateOutOfRange {
+        line: usize,
+    },
+    MissingBlockPosition {
+        line: usize,
+    },
+    InvalidNode {
+        line: usize,
+    },
+    UnknownNode {
+        line: usize,
+        node: usize,
+    },
+}
+
+impl fmt::Display for PatternError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::MissingHeader(header) => write!(f, "the pattern has no '{}' header line", header),
+            Self::InvalidHeader(header) => write!(f, "invalid header line '{}'", header),
+            Self::UnsupportedState(state) => write!(
+                f,
+                "the format only has live and dead cells, the pattern has cells in state {}",
+                state
+            ),
+            Self::UnexpectedCharacter { line, character } => {
+                write!(f, "line {}: unexpected character '{}'", line, character)
+            }
+            Self::UnterminatedState { line } => {
+                write!(
+                    f,
+                    "line {}: a state prefix is not followed by a state letter",
+                    line
+                )
+            }
+            Self::InvalidState { line, state } => {
+                write!(
+                    f,
+                    "line {}: state {} is above the highest state 255",
+                    line, state
+                )
+            }
+            Self::RunTooLong { line } => {
+                write!(
+                    f,
+                    "line {}: a run of cells reaches past the width or height in the header",
+                    line
+                )
+            }
+            Self::InvalidCoordinates { line } => {
+                write!(
+                    f,
+                    "line {}: expected two whole numbers for the x and y coordinates",
+                    line
+                )
+            }
+            Self::CoordinateOutOfRange { line } => {
+                write!(
+                    f,
+                    "line {}: the coordinates are too far from the origin",
+                    line
+                )
+            }
+            Self::MissingBlockPosition { line } => {
+                write!(
+                    f,
+                    "line {}: cells must come after a '#P' block position",
+                    line
+                )
+            }
+            Self::InvalidNode { line } => {
+                write!(
+                    f,
+                    "line {}: expected a node level followed by four child nodes",
+                    line
+                )
+            }
+            Self::UnknownNode { line, node } => {
+                write!(
+                    f,
+                    "line {}: node {} is not defined before this line",
+                    line, node
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for PatternError {}
@@ -0,0 +1,65 @@
+//! The plaintext `.cells` format, see <https://conwaylife.com/wiki/Plaintext>
+
+use std::fmt::Write;
+
+use crate::pattern::{Pattern, PatternError};
+use crate::state::Coord;
+
+/// Reads the cells where they are drawn in the file. Blank rows and columns before the first live
+/// cell are kept, so the pattern covers every row and column of the file.
+pub fn read(input: &str) -> Result<Pattern, PatternError> {
+    let mut pattern = Pattern::default();
+    let mut y = 0;
+    for (i, line) in input.lines().enumerate() {
+        let line = line.trim_end();
+        if let Some(comment) = line.strip_prefix('!') {
+            if let Some(name) = comment.strip_prefix("Name:") {
+                pattern.name = Some(name.trim().to_string());
+            } else if let Some(author) = comment.strip_prefix("Author:") {
+                pattern.author = Some(author.trim().to_string());
+            } else {
+                pattern.comments.push(comment.trim().to_string());
+            }
+            continue;
+        }
+        for (x, c) in line.chars().enumerate() {
+            match c {
+                '.' => (),
+                'O' | '*' => pattern.cells.push((Coord::new(x as i64, y), 1)),
+                _ => {
+                    return Err(PatternError::UnexpectedCharacter {
+                        line: i + 1,
+                        character: c,
+                    })
+                }
+            }
+        }
+        pattern.width = pattern.width.max(line.chars().count() as u64);
+        y += 1;
+    }
+    pattern.height = y as u64;
+    Ok(pattern)
+}
+
+pub fn write(pattern: &Pattern) -> Result<String, PatternError> {
+    pattern.check_two_states()?;
+    let mut out = String::new();
+    if let Some(name) = &pattern.name {
+        writeln!(out, "!Name: {}", name).unwrap();
+    }
+    if let Some(author) = &pattern.author {
+        writeln!(out, "!Author: {}", author).unwrap();
+    }
+    for comment in &pattern.comments {
+        writeln!(out, "!{}", comment).unwrap();
+    }
+    let mut rows = vec![vec!['.'; pattern.width as usize]; pattern.height as usize];
+    for (pos, _) in &pattern.cells {
+        rows[pos.y as usize][pos.x as usize] = 'O';
+    }
+    for row in rows {
+        out.extend(row);
+        out.push('\n');
+    }
+    Ok(out)
+}
@@ -0,0 +1,252 @@
+//! The run length encoded format used by most pattern collections, see
+//! <https://conwaylife.com/wiki/Run_Length_Encoded>
+
+use std::fmt::Write;
+
+use crate::pattern::{Pattern, PatternError};
+use crate::rule::Rule;
+use crate::state::Coord;
+
+/// The longest line the writer produces, as recommended by the format
+const MAX_LINE_LENGTH: usize = 70;
+
+pub fn read(input: &str) -> Result<Pattern, PatternError> {
+    let mut pattern = Pattern::default();
+    let mut lines = input
+        .lines()
+        .enumerate()
+        .map(|(i, line)| (i + 1, line.trim()));
+
+    let header = loop {
+        let (_, line) = lines
+            .next()
+            .ok_or(PatternError::MissingHeader("x = .., y = .."))?;
+        if let Some(comment) = line.strip_prefix('#') {
+            read_comment(&mut pattern, comment);
+        } else if !line.is_empty() {
+            break line;
+        }
+    };
+    read_header(&mut pattern, header)?;
+    // Rules with two states have no states above 24 to prefix, a rule that can't be parsed here
+    // may still have them
+    let two_states = pattern
+        .rule
+        .as_deref()
+        .and_then(|rule| rule.split(':').next()?.parse::<Rule>().ok())
+        .is_some_and(|rule| rule.states() == 2);
+
+    let (mut x, mut y): (i64, i64) = (0, 0);
+    let mut run: Option<i64> = None;
+    // The `p` to `y` prefix of a state above 24, waiting for its letter
+    let mut prefix: Option<u16> = None;
+    'body: for (line_number, line) in lines {
+        for c in line.chars() {
+            if prefix.is_some() && !c.is_ascii_uppercase() {
+                return Err(PatternError::UnterminatedState { line: line_number });
+            }
+            let too_long = || PatternError::RunTooLong { line: line_number };
+            let state = match c {
+                '0'..='9' => {
+                    let digit = c.to_digit(10).unwrap() as i64;
+                    let longer = run.unwrap_or(0).checked_mul(10);
+                    run = Some(
+                        longer
+                            .and_then(|run| run.checked_add(digit))
+                            .ok_or_else(too_long)?,
+                    );
+                    continue;
+                }
+                '!' => break 'body,
+                '$' => {
+                    y = y
+                        .checked_add(run.take().unwrap_or(1))
+                        .ok_or_else(too_long)?;
+                    x = 0;
+                    continue;
+                }
+                'b' | '.' => 0,
+                'p'..='y' if two_states => {
+                    return Err(PatternError::UnexpectedCharacter {
+                        line: line_number,
+                        character: c,
+                    })
+                }
+                'p'..='y' => {
+                    prefix = Some(c as u16 - b'p' as u16 + 1);
+                    continue;
+                }
+                'A'..='X' => {
+                    let state = prefix.take().unwrap_or(0) * 24 + c as u16 - b'A' as u16 + 1;
+                    u8::try_from(state).map_err(|_| PatternError::InvalidState {
+                        line: line_number,
+                        state,
+                    })?
+                }
+                // Two state patterns may use any other letter for live cells
+                'a'..='z' => 1,
+                _ if c.is_whitespace() => continue,
+                _ => {
+                    return Err(PatternError::UnexpectedCharacter {
+                        line: line_number,
+                        character: c,
+                    })
+                }
+            };
+            let count = run.take().unwrap_or(1);
+            let end = x.checked_add(count).ok_or_else(too_long)?;
+            if state != 0 {
+                // Only runs of live cells take memory, they have to fit into the header's size
+                if end as u64 > pattern.width || y as u64 >= pattern.height {
+                    return Err(too_long());
+                }
+                pattern
+                    .cells
+                    .extend((x..end).map(|x| (Coord::new(x, y), state)));
+            }
+            x = end;
+        }
+    }
+    Ok(pattern)
+}
+
+fn read_comment(pattern: &mut Pattern, comment: &str) {
+    let mut chars = comment.chars();
+    let kind = chars.next();
+    let text = chars.as_str().trim().to_string();
+    match kind {
+        Some('N') => pattern.name = Some(text),
+        Some('O') => pattern.author = Some(text),
+        Some('C' | 'c') => pattern.comments.push(text),
+        _ => (),
+    }
+}
+
+fn read_header(pattern: &mut Pattern, header: &str) -> Result<(), PatternError> {
+    let invalid = || PatternError::InvalidHeader(header.to_string());
+    let (mut width, mut height) = (None, None);
+    // The rule comes last and can contain commas itself, like "B3/S23:T50,50"
+    let (items, rule) = match header.find("rule") {
+        Some(start) => (
+            header[..start].trim_end().trim_end_matches(','),
+            Some(&header[start..]),
+        ),
+        None => (header, None),
+    };
+    for item in items.split(',') {
+        let (key, value) = item.split_once('=').ok_or_else(invalid)?;
+        let value = value.trim();
+        match key.trim() {
+            "x" => width = Some(value.parse().map_err(|_| invalid())?),
+            "y" => height = Some(value.parse().map_err(|_| invalid())?),
+            _ => (),
+        }
+    }
+    if let Some(rule) = rule {
+        let (_, value) = rule.split_once('=').ok_or_else(invalid)?;
+        pattern.rule = Some(value.trim().to_string());
+    }
+    pattern.width = width.ok_or_else(invalid)?;
+    pattern.height = height.ok_or_else(invalid)?;
+    Ok(())
+}
+
+pub fn write(pattern: &Pattern) -> String {
+    let mut out = String::new();
+    if let Some(name) = &pattern.name {
+        writeln!(out, "#N {}", name).unwrap();
+    }
+    if let Some(author) = &pattern.author {
+        writeln!(out, "#O {}", author).unwrap();
+    }
+    for comment in &pattern.comments {
+        writeln!(out, "#C {}", comment).unwrap();
+    }
+    write!(out, "x = {}, y = {}", pattern.width, pattern.height).unwrap();
+    if let Some(rule) = &pattern.rule {
+        write!(out, ", rule = {}", rule).unwrap();
+    }
+    out.push('\n');
+
+    let multi_state = pattern.cells.iter().any(|(_, state)| *state > 1);
+    let height = pattern
+        .cells
+        .iter()
+        .map(|(pos, _)| pos.y as usize + 1)
+        .max()
+        .unwrap_or(0);
+    let mut rows: Vec<Vec<(i64, u8)>> = vec![vec![]; height];
+    for (pos, state) in &pattern.cells {
+        rows[pos.y as usize].push((pos.x, *state));
+    }
+
+    let mut body = BodyWriter::default();
+    let mut empty_rows = 0;
+    for row in &mut rows {
+        if row.is_empty() {
+            empty_rows += 1;
+            continue;
+        }
+        if body.written {
+            body.push(empty_rows + 1, "$");
+        }
+        empty_rows = 0;
+        row.sort_unstable();
+        let mut x = 0;
+        let mut i = 0;
+        while i < row.len() {
+            let (start, state) = row[i];
+            let mut end = start + 1;
+            i += 1;
+            while i < row.len() && row[i] == (end, state) {
+                end += 1;
+                i += 1;
+            }
+            if start > x {
+                body.push((start - x) as u64, if multi_state { "." } else { "b" });
+            }
+            body.push((end - start) as u64, &state_symbol(state, multi_state));
+            x = end;
+        }
+    }
+    body.push(1, "!");
+    out.push_str(&body.out);
+    out.push('\n');
+    out
+}
+
+fn state_symbol(state: u8, multi_state: bool) -> String {
+    if !multi_state {
+        return "o".to_string();
+    }
+    let letter = (b'A' + (state - 1) % 24) as char;
+    match (state - 1) / 24 {
+        0 => letter.to_string(),
+        prefix => format!("{}{}", (b'p' + prefix - 1) as char, letter),
+    }
+}
+
+/// Collects runs and wraps them onto lines of at most [`MAX_LINE_LENGTH`] characters
+#[derive(Default)]
+struct BodyWriter {
+    out: String,
+    line_length: usize,
+    written: bool,
+}
+
+impl BodyWriter {
+    fn push(&mut self, count: u64, symbol: &str) {
+        let run = if count == 1 {
+            symbol.to_string()
+        } else {
+            format!("{}{}", count, symbol)
+        };
+        if self.line_length + run.len() > MAX_LINE_LENGTH {
+            self.out.push('\n');
+            self.line_length = 0;
+        }
+        self.line_length += run.len();
+        self.out.push_str(&run);
+        self.written = true;
+    }
+}
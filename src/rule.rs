@@ -0,0 +1,446 @@
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use crate::isotropic::Isotropic;
+use crate::ltl::LargerThanLife;
+use crate::ruletable::RuleTable;
+use crate::state::{HEXAGONAL_NEIGHBORS, NEIGHBORS, VON_NEUMANN_NEIGHBORS};
+
+/// Whether a cell is alive in the next generation, for each arrangement of the cell and its 8
+/// neighbors. Neighborhoods are bits in the order of `isotropic::NEIGHBOR_BITS`.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct TransitionTable([u64; 8]);
+
+impl TransitionTable {
+    pub fn get(&self, alive: bool, neighborhood: u8) -> bool {
+        let index = (alive as usize) << 8 | neighborhood as usize;
+        self.0[index / 64] & 1 << (index % 64) != 0
+    }
+
+    pub fn set(&mut self, alive: bool, neighborhood: u8) {
+        let index = (alive as usize) << 8 | neighborhood as usize;
+        self.0[index / 64] |= 1 << (index % 64);
+    }
+}
+
+/// The rule the cells follow
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Rule {
+    LifeLike(LifeLike),
+    Isotropic(Isotropic),
+    LargerThanLife(LargerThanLife),
+    /// A rule loaded from a .rule file, shared since its tables can be large
+    Table(Arc<RuleTable>),
+}
+
+impl Rule {
+    /// The number of cell states, 2 unless cells can be dying. Rule tables may use all 256
+    /// states a cell can store.
+    pub fn states(&self) -> u16 {
+        match self {
+            Self::LifeLike(rule) => rule.states.into(),
+            Self::Isotropic(rule) => rule.states.into(),
+            Self::LargerThanLife(rule) => rule.states.into(),
+            Self::Table(rule) => rule.states,
+        }
+    }
+
+    /// Whether the cells are hexagons, which only Life-like rules support
+    pub fn is_hexagonal(&self) -> bool {
+        matches!(self, Self::LifeLike(rule) if rule.neighbors == Neighbors::Hexagonal)
+    }
+
+    /// The transition table of two state rules on the 8 neighbors around a cell. Rules with B0
+    /// have none, HashLife can't run them.
+    pub fn transition_table(&self) -> Option<TransitionTable> {
+        if self.births_in_empty_space() {
+            return None;
+        }
+        match self {
+            Self::LifeLike(rule) if !rule.is_generations() => Some(rule.transition_table()),
+            Self::Isotropic(rule) if rule.states == 2 => Some(rule.table),
+            _ => None,
+        }
+    }
+
+    /// Whether dead cells without live neighbors are born (B0). The whole unbounded plane would
+    /// come alive at once, so only bounded grids run these rules.
+    pub fn births_in_empty_space(&self) -> bool {
+        match self {
+            Self::LifeLike(rule) => rule.birth[0],
+            Self::Isotropic(rule) => rule.table.get(false, 0),
+            Self::LargerThanLife(rule) => rule.next_cell(0, 0) != 0,
+            Self::Table(rule) => {
+                let inputs = rule.neighborhood.offsets().len() + 1;
+                rule.next_cell(&[0; 9][..inputs]) != 0
+            }
+        }
+    }
+}
+
+impl Default for Rule {
+    fn default() -> Self {
+        Self::LifeLike(LifeLike::default())
+    }
+}
+
+/// The cells Life-like rules count as neighbors, picked with Golly's "H" and "V" suffixes
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Neighbors {
+    /// The 8 cells around a cell
+    Moore,
+    /// The 6 cells around a hexagonal cell
+    Hexagonal,
+    /// The 4 cells sharing an edge with a cell
+    VonNeumann,
+}
+
+impl Neighbors {
+    pub fn offsets(self) -> &'static [[i64; 2]] {
+        match self {
+            Self::Moore => &NEIGHBORS,
+            Self::Hexagonal => &HEXAGONAL_NEIGHBORS,
+            Self::VonNeumann => &VON_NEUMANN_NEIGHBORS,
+        }
+    }
+
+    /// The neighbors as bits of a neighborhood, in the order of `isotropic::NEIGHBOR_BITS`
+    fn mask(self) -> u8 {
+        match self {
+            Self::Moore => 0b1111_1111,
+            // Without the north-east and south-west cells
+            Self::Hexagonal => 0b1101_1101,
+            Self::VonNeumann => 0b0101_0101,
+        }
+    }
+
+    fn suffix(self) -> &'static str {
+        match self {
+            Self::Moore => "",
+            Self::Hexagonal => "H",
+            Self::VonNeumann => "V",
+        }
+    }
+}
+
+/// An outer-totalistic rule on the `neighbors` of a cell. `birth[n]` is true if a dead cell with `n`
+/// live neighbors comes alive, `survival[n]` is true if a live cell with `n` live neighbors stays
+/// alive.
+///
+/// Rules with more than two `states` are Generations rules. State 0 is dead and 1 is alive, live
+/// cells that don't survive go through the dying states 2 to `states - 1` one generation at a
+/// time before they are dead. Only live cells count as neighbors, and only dead cells can be born.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct LifeLike {
+    pub birth: [bool; 9],
+    pub survival: [bool; 9],
+    pub states: u8,
+    pub neighbors: Neighbors,
+}
+
+impl LifeLike {
+    pub fn next_state(&self, alive: bool, live_neighbors: usize) -> bool {
+        if alive {
+            self.survival[live_neighbors]
+        } else {
+            self.birth[live_neighbors]
+        }
+    }
+
+    /// The state after `state`, for a cell with `live_neighbors` neighbors in state 1
+    pub fn next_cell(&self, state: u8, live_neighbors: usize) -> u8 {
+        match state {
+            0 => self.birth[live_neighbors] as u8,
+            1 if self.survival[live_neighbors] => 1,
+            _ if state + 1 < self.states => state + 1,
+            _ => 0,
+        }
+    }
+
+    /// Whether cells can be in dying states
+    pub fn is_generations(&self) -> bool {
+        self.states > 2
+    }
+
+    pub fn transition_table(&self) -> TransitionTable {
+        let mut table = TransitionTable::default();
+        for alive in [false, true] {
+            for neighborhood in 0..=255u8 {
+                let live_neighbors = (neighborhood & self.neighbors.mask()).count_ones() as usize;
+                if self.next_state(alive, live_neighbors) {
+                    table.set(alive, neighborhood);
+                }
+            }
+        }
+        table
+    }
+
+    /// The rule in the survival/birth notation used by Life 1.05 files, like "23/3", followed by
+    /// the number of states for Generations rules, like "345/2/4"
+    pub fn sb_notation(&self) -> String {
+        let digits = |counts: &[bool; 9]| -> String {
+            (0..9)
+                .filter(|n| counts[*n])
+                .map(|n| n.to_string())
+                .collect()
+        };
+        let mut notation = format!("{}/{}", digits(&self.survival), digits(&self.birth));
+        if self.is_generations() {
+            notation.push_str(&format!("/{}", self.states));
+        }
+        notation.push_str(self.neighbors.suffix());
+        notation
+    }
+}
+
+impl Default for LifeLike {
+    /// Conway's Game of Life, B3/S23
+    fn default() -> Self {
+        let mut birth = [false; 9];
+        let mut survival = [false; 9];
+        birth[3] = true;
+        survival[2] = true;
+        survival[3] = true;
+        Self {
+            birth,
+            survival,
+            states: 2,
+            neighbors: Neighbors::Moore,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RuleParseError {
+    Empty,
+    MissingSeparator,
+    MissingSection(char),
+    DuplicateSection(char),
+    DigitWithoutSection(char),
+    InvalidNeighborCount(char),
+    CountOutsideNeighborhood(usize),
+    InvalidStateCount(String),
+    InvalidParameter(String),
+    InvalidNeighborhoodLetter(String),
+    UnexpectedCharacter(char),
+}
+
+impl fmt::Display for RuleParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Empty => write!(f, "the rulestring is empty"),
+            Self::MissingSeparator => write!(f, "expected a '/' between survival and birth counts"),
+            Self::MissingSection(c) => write!(f, "the rulestring has no '{}' section", c),
+            Self::DuplicateSection(c) => write!(f, "the '{}' section appears more than once", c),
+            Self::DigitWithoutSection(c) => {
+                write!(
+                    f,
+                    "neighbor count '{}' does not belong to a 'B' or 'S' section",
+                    c
+                )
+            }
+            Self::InvalidNeighborCount(c) => {
+                write!(
+                    f,
+                    "'{}' is not a neighbor count, expected a digit from 0 to 8",
+                    c
+                )
+            }
+            Self::CountOutsideNeighborhood(count) => {
+                write!(f, "the neighborhood has fewer than {} cells", count)
+            }
+            Self::InvalidStateCount(states) => {
+                write!(
+                    f,
+                    "'{}' is not a number of states, expected 2 to 255",
+                    states
+                )
+            }
+            Self::InvalidParameter(parameter) => {
+                write!(f, "invalid rule parameter '{}'", parameter)
+            }
+            Self::InvalidNeighborhoodLetter(letter) => {
+                write!(f, "'{}' is not a neighborhood in Hensel notation", letter)
+            }
+            Self::UnexpectedCharacter(c) => write!(f, "unexpected character '{}'", c),
+        }
+    }
+}
+
+impl std::error::Error for RuleParseError {}
+
+fn parse_counts(digits: &str) -> Result<[bool; 9], RuleParseError> {
+    let mut counts = [false; 9];
+    for c in digits.chars() {
+        match c.to_digit(10) {
+            Some(n) if n <= 8 => counts[n as usize] = true,
+            Some(_) => return Err(RuleParseError::InvalidNeighborCount(c)),
+            None => return Err(RuleParseError::UnexpectedCharacter(c)),
+        }
+    }
+    Ok(counts)
+}
+
+fn parse_states(digits: &str) -> Result<u8, RuleParseError> {
+    match digits.parse() {
+        Ok(states) if states >= 2 => Ok(states),
+        _ => Err(RuleParseError::InvalidStateCount(digits.to_string())),
+    }
+}
+
+/// Parses "B36/S23" style rulestrings, and "B2/S/C3" style Generations rulestrings. The sections
+/// may come in either order and the '/' is optional.
+fn parse_bs_notation(s: &str) -> Result<LifeLike, RuleParseError> {
+    let mut birth = None;
+    let mut survival = None;
+    let mut states: Option<String> = None;
+    // The section the following digits are added to, 'B', 'S' or 'C'
+    let mut current = None;
+
+    for c in s.chars() {
+        match c.to_ascii_uppercase() {
+            section @ ('B' | 'S') => {
+                let counts = if section == 'B' {
+                    &mut birth
+                } else {
+                    &mut survival
+                };
+                if counts.replace([false; 9]).is_some() {
+                    return Err(RuleParseError::DuplicateSection(section));
+                }
+                current = Some(section);
+            }
+            'C' => {
+                if states.replace(String::new()).is_some() {
+                    return Err(RuleParseError::DuplicateSection('C'));
+                }
+                current = Some('C');
+            }
+            '/' => current = None,
+            '0'..='9' if current == Some('C') => states.as_mut().unwrap().push(c),
+            '0'..='8' => {
+                let counts = match current {
+                    Some('B') => birth.as_mut().unwrap(),
+                    Some(_) => survival.as_mut().unwrap(),
+                    None => return Err(RuleParseError::DigitWithoutSection(c)),
+                };
+                counts[c.to_digit(10).unwrap() as usize] = true;
+            }
+            '9' => return Err(RuleParseError::InvalidNeighborCount(c)),
+            _ => return Err(RuleParseError::UnexpectedCharacter(c)),
+        }
+    }
+    Ok(LifeLike {
+        birth: birth.ok_or(RuleParseError::MissingSection('B'))?,
+        survival: survival.ok_or(RuleParseError::MissingSection('S'))?,
+        states: states.map_or(Ok(2), |states| parse_states(&states))?,
+        neighbors: Neighbors::Moore,
+    })
+}
+
+/// Parses "23/3" style rulestrings, survival counts first, and "345/2/4" style Generations
+/// rulestrings with the number of states last.
+fn parse_sb_notation(s: &str) -> Result<LifeLike, RuleParseError> {
+    let (survival, birth) = s.split_once('/').ok_or(RuleParseError::MissingSeparator)?;
+    let (birth, states) = match birth.split_once('/') {
+        Some((birth, states)) => (birth, parse_states(states)?),
+        None => (birth, 2),
+    };
+    Ok(LifeLike {
+        birth: parse_counts(birth)?,
+        survival: parse_counts(survival)?,
+        states,
+        neighbors: Neighbors::Moore,
+    })
+}
+
+impl FromStr for LifeLike {
+    type Err = RuleParseError;
+
+    /// A trailing "H" or "V" like in "B2/S34H" counts hexagonal or von Neumann neighbors
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if s.is_empty() {
+            return Err(RuleParseError::Empty);
+        }
+        let (s, neighbors) = match s.chars().last().map(|c| c.to_ascii_uppercase()) {
+            Some('H') => (&s[..s.len() - 1], Neighbors::Hexagonal),
+            Some('V') => (&s[..s.len() - 1], Neighbors::VonNeumann),
+            _ => (s, Neighbors::Moore),
+        };
+        let mut rule = if s.chars().any(|c| c.is_ascii_alphabetic()) {
+            parse_bs_notation(s)?
+        } else {
+            parse_sb_notation(s)?
+        };
+        let max = neighbors.offsets().len();
+        if let Some(count) = (max + 1..9).find(|n| rule.birth[*n] || rule.survival[*n]) {
+            return Err(RuleParseError::CountOutsideNeighborhood(count));
+        }
+        rule.neighbors = neighbors;
+        Ok(rule)
+    }
+}
+
+impl FromStr for Rule {
+    type Err = RuleParseError;
+
+    /// Larger than Life rules start with their radius, like "R5,C0,...". Rules with Hensel
+    /// letters that turn out to be totalistic are Life-like rules.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let mut chars = s.chars();
+        if chars.next().is_some_and(|c| c.eq_ignore_ascii_case(&'R'))
+            && chars.next().is_some_and(|c| c.is_ascii_digit())
+        {
+            return Ok(Self::LargerThanLife(s.parse()?));
+        }
+        let life_like_error = match s.parse::<LifeLike>() {
+            Ok(rule) => return Ok(Self::LifeLike(rule)),
+            Err(err) => err,
+        };
+        let has_letters = s.chars().any(|c| "-aceijknqrtwyz".contains(c));
+        match s.parse::<Isotropic>() {
+            Ok(rule) => Ok(match rule.totalistic_counts() {
+                Some((birth, survival)) => Self::LifeLike(LifeLike {
+                    birth,
+                    survival,
+                    states: rule.states,
+                    neighbors: Neighbors::Moore,
+                }),
+                None => Self::Isotropic(rule),
+            }),
+            Err(err) if has_letters => Err(err),
+            Err(_) => Err(life_like_error),
+        }
+    }
+}
+
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::LifeLike(rule) => rule.fmt(f),
+            Self::Isotropic(rule) => rule.fmt(f),
+            Self::LargerThanLife(rule) => rule.fmt(f),
+            Self::Table(rule) => rule.fmt(f),
+        }
+    }
+}
+
+impl fmt::Display for LifeLike {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "B")?;
+        for (n, _) in self.birth.iter().enumerate().filter(|(_, b)| **b) {
+            write!(f, "{}", n)?;
+        }
+        write!(f, "/S")?;
+        for (n, _) in self.survival.iter().enumerate().filter(|(_, s)| **s) {
+            write!(f, "{}", n)?;
+        }
+        if self.is_generations() {
+            write!(f, "/C{}", self.states)?;
+        }
+        write!(f, "{}", self.neighbors.suffix())
+    }
+}
@@ -0,0 +1,602 @@
+use std::collections::{BTreeSet, HashMap};
+use std::fmt;
+use std::str::FromStr;
+
+use crate::isotropic::NEIGHBOR_BITS;
+
+/// A set of cell states, one bit per state
+type StateSet = [u64; 4];
+
+fn single_state(state: u8) -> StateSet {
+    let mut set = [0; 4];
+    set[state as usize / 64] |= 1 << (state % 64);
+    set
+}
+
+fn set_states(set: &StateSet) -> impl Iterator<Item = u8> + '_ {
+    (0..=255u8).filter(move |state| set[*state as usize / 64] & 1 << (state % 64) != 0)
+}
+
+/// The cells the inputs of a rule table look at besides the cell itself
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum TableNeighborhood {
+    Moore,
+    VonNeumann,
+    /// The 6 neighbors of Life-like rules ending in "H"
+    Hexagonal,
+    /// The left and right cells, every row is a separate one dimensional automaton
+    OneDimensional,
+}
+
+impl TableNeighborhood {
+    /// The neighbors in the order of the inputs of a transition, clockwise from the north
+    pub fn offsets(self) -> &'static [[i64; 2]] {
+        match self {
+            Self::Moore => &NEIGHBOR_BITS,
+            Self::VonNeumann => &[[0, 1], [1, 0], [0, -1], [-1, 0]],
+            Self::Hexagonal => &[[0, 1], [1, 0], [1, -1], [0, -1], [-1, 0], [-1, 1]],
+            Self::OneDimensional => &[[-1, 0], [1, 0]],
+        }
+    }
+}
+
+impl FromStr for TableNeighborhood {
+    type Err = RuleTableError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "moore" => Ok(Self::Moore),
+            "vonneumann" => Ok(Self::VonNeumann),
+            "hexagonal" => Ok(Self::Hexagonal),
+            "onedimensional" => Ok(Self::OneDimensional),
+            _ => Err(RuleTableError::UnknownNeighborhood(s.to_string())),
+        }
+    }
+}
+
+/// The arrangements of the neighbors a transition also applies to
+enum Symmetry {
+    /// Maps from each neighbor to where it ends up, one for every rotation or reflection
+    Group(Vec<Vec<usize>>),
+    /// Any order of the neighbors
+    Permute,
+}
+
+/// Parses Golly's symmetries, like "rotate4reflect". Rotations turn the ring of neighbors by
+/// whole steps, reflections mirror it from left to right.
+fn parse_symmetry(name: &str, neighborhood: TableNeighborhood) -> Result<Symmetry, RuleTableError> {
+    let neighbors = neighborhood.offsets().len();
+    let unknown = || RuleTableError::UnknownSymmetry(name.to_string());
+    let (rotations, reflect) = match name.to_ascii_lowercase().as_str() {
+        "none" => (1, false),
+        "permute" => return Ok(Symmetry::Permute),
+        // The left and right neighbors of one dimensional rules are swapped by a half turn
+        "reflect" if neighborhood == TableNeighborhood::OneDimensional => (2, false),
+        "reflect_horizontal" if neighborhood != TableNeighborhood::OneDimensional => (1, true),
+        name => {
+            let rotate = name.strip_prefix("rotate").ok_or_else(unknown)?;
+            let (count, reflect) = match rotate.strip_suffix("reflect") {
+                Some(count) => (count, true),
+                None => (rotate, false),
+            };
+            let count: usize = count.parse().map_err(|_| unknown())?;
+            if neighbors % count != 0 {
+                return Err(unknown());
+            }
+            (count, reflect)
+        }
+    };
+    let step = neighbors / rotations;
+    let mut maps = vec![];
+    for turn in (0..neighbors).step_by(step) {
+        maps.push((0..neighbors).map(|i| (i + turn) % neighbors).collect());
+        if reflect {
+            maps.push(
+                (0..neighbors)
+                    .map(|i| (neighbors - i + turn) % neighbors)
+                    .collect(),
+            );
+        }
+    }
+    Ok(Symmetry::Group(maps))
+}
+
+/// Every way to pick `count` states out of `states` when the order doesn't matter, each in
+/// ascending order
+fn multisets(states: &[u8], count: usize) -> Vec<Vec<u8>> {
+    if count == 0 {
+        return vec![vec![]];
+    }
+    let mut all = vec![];
+    for (i, state) in states.iter().enumerate() {
+        for rest in multisets(&states[i..], count - 1) {
+            let mut multiset = vec![*state];
+            multiset.extend(rest);
+            all.push(multiset);
+        }
+    }
+    all
+}
+
+/// The states neighbors in any order can have to match `sets`, as ascending lists. Equal sets
+/// are picked from together, so the same list isn't produced once for every order of them.
+fn sorted_choices(sets: &[StateSet]) -> Vec<Vec<u8>> {
+    let mut sets = sets.to_vec();
+    sets.sort_unstable();
+    let mut choices = vec![vec![]];
+    let mut start = 0;
+    while start < sets.len() {
+        let count = sets[start..]
+            .iter()
+            .take_while(|set| **set == sets[start])
+            .count();
+        let states: Vec<u8> = set_states(&sets[start]).collect();
+        let picks = multisets(&states, count);
+        start += count;
+        choices = choices
+            .iter()
+            .flat_map(|choice| {
+                picks.iter().map(move |pick| {
+                    let mut choice = choice.clone();
+                    choice.extend(pick);
+                    choice
+                })
+            })
+            .collect();
+    }
+    for choice in &mut choices {
+        choice.sort_unstable();
+    }
+    choices
+}
+
+/// One transition with its variables bound: the sets of states the cell and its neighbors may be
+/// in, and the state of the cell afterwards
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Debug)]
+struct Transition {
+    inputs: Vec<StateSet>,
+    output: u8,
+}
+
+/// A multi-state rule from the @TABLE section of a Golly .rule file, like WireWorld or Langton's
+/// loops. Each transition lists the states of a cell and its neighbors and the state the cell
+/// changes to; the first transition that matches applies. Cells no transition matches keep their
+/// state.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct RuleTable {
+    pub name: String,
+    /// Up to 256, every state a cell can store
+    pub states: u16,
+    pub neighborhood: TableNeighborhood,
+    /// The colors of the states given in the @COLORS section
+    pub colors: Vec<Option<[u8; 3]>>,
+    /// Whether the neighbors may be in any order. Transitions then list the states of the
+    /// neighbors in ascending order, and the neighbors of a cell are sorted to match them.
+    permute: bool,
+    outputs: Vec<u8>,
+    /// The transitions accepting each state of each input as bits, `words` per input and state,
+    /// so matching a cell ANDs one row of bits per input
+    matches: Vec<u64>,
+    words: usize,
+}
+
+impl RuleTable {
+    /// The number of cells a transition looks at, the cell itself included
+    pub fn inputs(&self) -> usize {
+        self.neighborhood.offsets().len() + 1
+    }
+
+    /// The next state of a cell. `cells` holds its state followed by the states of its
+    /// neighbors in the order of `TableNeighborhood::offsets`.
+    pub fn next_cell(&self, cells: &[u8]) -> u8 {
+        let center = cells[0];
+        if cells.iter().any(|state| u16::from(*state) >= self.states) {
+            return center;
+        }
+        let mut sorted = [0; 9];
+        let cells = if self.permute {
+            let sorted = &mut sorted[..cells.len()];
+            sorted.copy_from_slice(cells);
+            sorted[1..].sort_unstable();
+            sorted
+        } else {
+            cells
+        };
+        let row = |input: usize, state: u8| {
+            let start = (input * self.states as usize + state as usize) * self.words;
+            &self.matches[start..start + self.words]
+        };
+        for word in 0..self.words {
+            let matching = cells
+                .iter()
+                .enumerate()
+                .fold(!0u64, |matching, (input, state)| {
+                    matching & row(input, *state)[word]
+                });
+            if matching != 0 {
+                return self.outputs[word * 64 + matching.trailing_zeros() as usize];
+            }
+        }
+        center
+    }
+
+    fn new(
+        name: String,
+        states: u16,
+        neighborhood: TableNeighborhood,
+        transitions: Vec<Transition>,
+        colors: Vec<Option<[u8; 3]>>,
+        permute: bool,
+    ) -> Self {
+        let inputs = neighborhood.offsets().len() + 1;
+        let words = transitions.len().div_ceil(64);
+        let mut matches = vec![0; inputs * states as usize * words];
+        for (index, transition) in transitions.iter().enumerate() {
+            for (input, set) in transition.inputs.iter().enumerate() {
+                for state in set_states(set).filter(|state| u16::from(*state) < states) {
+                    let start = (input * states as usize + state as usize) * words;
+                    matches[start + index / 64] |= 1 << (index % 64);
+                }
+            }
+        }
+        Self {
+            name,
+            states,
+            neighborhood,
+            colors,
+            permute,
+            outputs: transitions
+                .iter()
+                .map(|transition| transition.output)
+                .collect(),
+            matches,
+            words,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RuleTableError {
+    MissingName,
+    MissingTable,
+    TreeNotSupported,
+    MissingStateCount,
+    InvalidStateCount(String),
+    UnknownNeighborhood(String),
+    UnknownSymmetry(String),
+    /// A line that can't be parsed, with its line number
+    InvalidLine(usize, String),
+    UnknownVariable(usize, String),
+    InvalidState(usize, String),
+    WrongInputCount(usize),
+}
+
+impl fmt::Display for RuleTableError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::MissingName => write!(f, "the rule file has no @RULE line"),
+            Self::MissingTable => write!(f, "the rule file has no @TABLE section"),
+            Self::TreeNotSupported => write!(f, "@TREE rules are not supported"),
+            Self::MissingStateCount => write!(f, "n_states must come before the transitions"),
+            Self::InvalidStateCount(states) => {
+                write!(f, "'{}' is not a number of states, expected 2 to 256", states)
+            }
+            Self::UnknownNeighborhood(neighborhood) => write!(
+                f,
+                "unknown neighborhood '{}', expected Moore, vonNeumann, hexagonal or oneDimensional",
+                neighborhood
+            ),
+            Self::UnknownSymmetry(symmetry) => {
+                write!(f, "unknown symmetries '{}' for this neighborhood", symmetry)
+            }
+            Self::InvalidLine(line, text) => write!(f, "line {}: can't read '{}'", line, text),
+            Self::UnknownVariable(line, name) => write!(f, "line {}: unknown variable '{}'", line, name),
+            Self::InvalidState(line, state) => write!(f, "line {}: '{}' is not a state of the rule", line, state),
+            Self::WrongInputCount(line) => {
+                write!(f, "line {}: the number of states doesn't fit the neighborhood", line)
+            }
+        }
+    }
+}
+
+impl std::error::Error for RuleTableError {}
+
+/// A state or a variable of a transition
+enum Token<'a> {
+    State(u8),
+    Variable(&'a str),
+}
+
+/// Parses the states and variables of the @TABLE section, reading transitions once the
+/// neighborhood, symmetries and variables before them are known
+struct TableParser<'a> {
+    states: Option<u16>,
+    neighborhood: TableNeighborhood,
+    /// The symmetries as written, read once the neighborhood they apply to is known
+    symmetry_name: Option<&'a str>,
+    symmetry: Option<Symmetry>,
+    variables: HashMap<&'a str, StateSet>,
+    transitions: Vec<Transition>,
+    /// Transitions already added, symmetric variants of a transition often repeat
+    seen: BTreeSet<Transition>,
+}
+
+impl<'a> TableParser<'a> {
+    fn parse_state(&self, number: usize, token: &str) -> Result<u8, RuleTableError> {
+        let states = self.states.ok_or(RuleTableError::MissingStateCount)?;
+        match token.parse::<u8>() {
+            Ok(state) if u16::from(state) < states => Ok(state),
+            _ => Err(RuleTableError::InvalidState(number, token.to_string())),
+        }
+    }
+
+    fn parse_token(&self, number: usize, token: &'a str) -> Result<Token<'a>, RuleTableError> {
+        if token.starts_with(|c: char| c.is_ascii_digit()) {
+            Ok(Token::State(self.parse_state(number, token)?))
+        } else if self.variables.contains_key(token) {
+            Ok(Token::Variable(token))
+        } else {
+            Err(RuleTableError::UnknownVariable(number, token.to_string()))
+        }
+    }
+
+    /// Parses "var a={0,1,2}". The states may also be other variables.
+    fn parse_variable(&mut self, number: usize, line: &'a str) -> Result<(), RuleTableError> {
+        let invalid = || RuleTableError::InvalidLine(number, line.to_string());
+        let (name, values) = line["var".len()..].split_once('=').ok_or_else(invalid)?;
+        let values = values
+            .trim()
+            .strip_prefix('{')
+            .and_then(|values| values.strip_suffix('}'))
+            .ok_or_else(invalid)?;
+        let mut set = [0; 4];
+        for value in values.split(',').map(str::trim) {
+            let values = match self.parse_token(number, value)? {
+                Token::State(state) => single_state(state),
+                Token::Variable(name) => self.variables[name],
+            };
+            for (word, values) in set.iter_mut().zip(values) {
+                *word |= values;
+            }
+        }
+        self.variables.insert(name.trim(), set);
+        Ok(())
+    }
+
+    /// Adds a transition, once for each value of the variables that appear more than once and
+    /// each arrangement of the neighbors its symmetries allow. Permuted neighbors are added
+    /// once for each sorted list of states they can match instead.
+    fn parse_transition(&mut self, number: usize, line: &'a str) -> Result<(), RuleTableError> {
+        let tokens: Vec<&str> = if line.contains(',') {
+            line.split(',').map(str::trim).collect()
+        } else {
+            // Without commas every character is a state or a variable
+            line.char_indices()
+                .filter(|(_, c)| !c.is_whitespace())
+                .map(|(i, c)| &line[i..i + c.len_utf8()])
+                .collect()
+        };
+        if tokens.len() != self.neighborhood.offsets().len() + 2 {
+            return Err(RuleTableError::WrongInputCount(number));
+        }
+        let tokens = tokens
+            .iter()
+            .map(|token| self.parse_token(number, token))
+            .collect::<Result<Vec<_>, _>>()?;
+        // Variables that appear more than once stand for the same state everywhere
+        let mut bound: Vec<&str> = vec![];
+        for (i, token) in tokens.iter().enumerate() {
+            if let Token::Variable(name) = token {
+                let repeated = tokens[i + 1..]
+                    .iter()
+                    .any(|other| matches!(other, Token::Variable(other) if other == name));
+                if repeated && !bound.contains(name) {
+                    bound.push(name);
+                }
+            }
+        }
+        if let Some(Token::Variable(name)) = tokens.last() {
+            if !bound.contains(name) {
+                return Err(RuleTableError::InvalidState(number, name.to_string()));
+            }
+        }
+        let choices: Vec<Vec<u8>> = bound
+            .iter()
+            .map(|name| set_states(&self.variables[name]).collect())
+            .collect();
+        let mut chosen = vec![0; bound.len()];
+        loop {
+            let value = |token: &Token| match token {
+                Token::State(state) => single_state(*state),
+                Token::Variable(name) => match bound.iter().position(|bound| bound == name) {
+                    Some(i) => single_state(choices[i][chosen[i]]),
+                    None => self.variables[name],
+                },
+            };
+            let output = set_states(&value(tokens.last().unwrap())).next().unwrap();
+            let inputs: Vec<StateSet> = tokens[..tokens.len() - 1].iter().map(value).collect();
+            self.add_symmetric(inputs, output);
+            // The next combination of bound values, like counting with mixed digits
+            match (0..chosen.len()).find(|i| chosen[*i] + 1 < choices[*i].len()) {
+                Some(i) => {
+                    chosen[i] += 1;
+                    chosen[..i].fill(0);
+                }
+                None => break,
+            }
+        }
+        Ok(())
+    }
+
+    fn add_symmetric(&mut self, inputs: Vec<StateSet>, output: u8) {
+        let center = inputs[0];
+        let neighbors = &inputs[1..];
+        let mut variants = vec![];
+        match &self.symmetry {
+            None => variants.push(neighbors.to_vec()),
+            Some(Symmetry::Group(maps)) => {
+                for map in maps {
+                    let mut variant = neighbors.to_vec();
+                    for (i, set) in neighbors.iter().enumerate() {
+                        variant[map[i]] = *set;
+                    }
+                    variants.push(variant);
+                }
+            }
+            Some(Symmetry::Permute) => {
+                for choice in sorted_choices(neighbors) {
+                    variants.push(choice.into_iter().map(single_state).collect());
+                }
+            }
+        }
+        for variant in variants {
+            let mut inputs = vec![center];
+            inputs.extend(variant);
+            let transition = Transition { inputs, output };
+            if self.seen.insert(transition.clone()) {
+                self.transitions.push(transition);
+            }
+        }
+    }
+
+    fn parse_line(&mut self, number: usize, line: &'a str) -> Result<(), RuleTableError> {
+        let invalid = || RuleTableError::InvalidLine(number, line.to_string());
+        if let Some((key, value)) = line.split_once(':') {
+            let value = value.trim();
+            match key.trim().to_ascii_lowercase().as_str() {
+                "n_states" => {
+                    self.states = match value.parse() {
+                        Ok(states) if (2..=256).contains(&states) => Some(states),
+                        _ => return Err(RuleTableError::InvalidStateCount(value.to_string())),
+                    }
+                }
+                "neighborhood" => self.neighborhood = value.parse()?,
+                "symmetries" => self.symmetry_name = Some(value),
+                _ => return Err(invalid()),
+            }
+        } else if line.starts_with("var") && line.contains('=') {
+            self.end_header()?;
+            self.parse_variable(number, line)?;
+        } else {
+            self.end_header()?;
+            self.parse_transition(number, line)?;
+        }
+        Ok(())
+    }
+
+    /// Reads the symmetries once the lines before the variables and transitions are known, the
+    /// neighborhood may come after them
+    fn end_header(&mut self) -> Result<(), RuleTableError> {
+        if let Some(name) = self.symmetry_name.take() {
+            self.symmetry = Some(parse_symmetry(name, self.neighborhood)?);
+        }
+        Ok(())
+    }
+}
+
+/// Parses one line of the @COLORS section, "state r g b" or a gradient "r1 g1 b1 r2 g2 b2"
+/// from state 1 to the last state
+fn parse_colors(
+    number: usize,
+    line: &str,
+    colors: &mut [Option<[u8; 3]>],
+) -> Result<(), RuleTableError> {
+    let values = line
+        .split(|c: char| c.is_whitespace() || c == ',')
+        .filter(|value| !value.is_empty())
+        .map(str::parse::<u8>)
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|_| RuleTableError::InvalidLine(number, line.to_string()))?;
+    match values[..] {
+        [state, r, g, b] => {
+            if let Some(color) = colors.get_mut(state as usize) {
+                *color = Some([r, g, b]);
+            }
+        }
+        [r1, g1, b1, r2, g2, b2] => {
+            let last = colors.len().saturating_sub(2).max(1);
+            for (i, color) in colors.iter_mut().enumerate().skip(1) {
+                let t = (i - 1) as f32 / last as f32;
+                let mix = |a: u8, b: u8| (a as f32 + (b as f32 - a as f32) * t).round() as u8;
+                *color = Some([mix(r1, r2), mix(g1, g2), mix(b1, b2)]);
+            }
+        }
+        _ => return Err(RuleTableError::InvalidLine(number, line.to_string())),
+    }
+    Ok(())
+}
+
+impl FromStr for RuleTable {
+    type Err = RuleTableError;
+
+    /// Parses the contents of a .rule file. Only the @RULE, @TABLE and @COLORS sections are
+    /// read, other sections like @ICONS are skipped.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut name = None;
+        let mut has_table = false;
+        let mut parser = TableParser {
+            states: None,
+            neighborhood: TableNeighborhood::Moore,
+            symmetry_name: None,
+            symmetry: None,
+            variables: HashMap::new(),
+            transitions: vec![],
+            seen: BTreeSet::new(),
+        };
+        let mut color_lines = vec![];
+        let mut section = "";
+        for (i, line) in s.lines().enumerate() {
+            let number = i + 1;
+            let line = line.split('#').next().unwrap().trim();
+            if line.is_empty() {
+                continue;
+            }
+            if let Some(header) = line.strip_prefix('@') {
+                let (header, rest) = header
+                    .split_once(char::is_whitespace)
+                    .unwrap_or((header, ""));
+                section = header;
+                match header {
+                    "RULE" => name = Some(rest.trim().to_string()),
+                    "TABLE" => has_table = true,
+                    "TREE" => return Err(RuleTableError::TreeNotSupported),
+                    _ => {}
+                }
+                continue;
+            }
+            match section {
+                "TABLE" => parser.parse_line(number, line)?,
+                // The number of states might not be known yet
+                "COLORS" => color_lines.push((number, line)),
+                _ => {}
+            }
+        }
+        let name = name
+            .filter(|name| !name.is_empty())
+            .ok_or(RuleTableError::MissingName)?;
+        if !has_table {
+            return Err(RuleTableError::MissingTable);
+        }
+        let states = parser.states.ok_or(RuleTableError::MissingStateCount)?;
+        parser.end_header()?;
+        let permute = matches!(parser.symmetry, Some(Symmetry::Permute));
+        let mut colors = vec![None; states as usize];
+        for (number, line) in color_lines {
+            parse_colors(number, line, &mut colors)?;
+        }
+        Ok(Self::new(
+            name,
+            states,
+            parser.neighborhood,
+            parser.transitions,
+            colors,
+            permute,
+        ))
+    }
+}
+
+impl fmt::Display for RuleTable {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name)
+    }
+}
@@ -0,0 +1,178 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::isotropic::{Isotropic, NEIGHBOR_BITS};
+use crate::ltl::LargerThanLife;
+use crate::rule::{LifeLike, Rule};
+use crate::ruletable::RuleTable;
+use crate::state::Coord;
+
+/// The smallest tiles Larger than Life rules are stepped in
+const MIN_TILE_SIDE: i64 = 64;
+
+/// An unbounded plane that only stores the coordinates and states of live and dying cells.
+///
+/// Only cells in the neighborhood of a live cell are evaluated in each generation. Rules where
+/// cells are born with zero neighbors (B0) would fill the whole plane, `StateGrid` doesn't step
+/// them on this engine.
+#[derive(Default, Clone)]
+pub struct SparseGrid {
+    cells: HashMap<Coord, u8>,
+    pub generation: u64,
+}
+
+impl SparseGrid {
+    pub fn get(&self, pos: Coord) -> u8 {
+        self.cells.get(&pos).copied().unwrap_or(0)
+    }
+
+    pub fn set(&mut self, pos: Coord, state: u8) {
+        if state != 0 {
+            self.cells.insert(pos, state);
+        } else {
+            self.cells.remove(&pos);
+        }
+    }
+
+    pub fn population(&self) -> u64 {
+        self.cells.len() as u64
+    }
+
+    /// The live and dying cells with their states
+    pub fn cells(&self) -> impl Iterator<Item = (Coord, u8)> + '_ {
+        self.cells.iter().map(|(pos, state)| (*pos, *state))
+    }
+
+    pub fn step(&mut self, rule: &Rule) {
+        match rule {
+            Rule::LifeLike(rule) => self.step_life_like(rule),
+            Rule::Isotropic(rule) => self.step_isotropic(rule),
+            Rule::Table(rule) => self.step_table(rule),
+            Rule::LargerThanLife(rule) => self.step_larger_than_life(rule),
+        }
+        self.generation += 1;
+    }
+
+    fn step_life_like(&mut self, rule: &LifeLike) {
+        let mut neighbor_counts: HashMap<Coord, usize> =
+            HashMap::with_capacity(self.cells.len() * 8);
+        for (pos, _) in self.cells.iter().filter(|(_, state)| **state == 1) {
+            for [dx, dy] in rule.neighbors.offsets() {
+                *neighbor_counts
+                    .entry(Coord::new(pos.x + dx, pos.y + dy))
+                    .or_insert(0) += 1;
+            }
+        }
+        let mut cells = HashMap::with_capacity(self.cells.len());
+        // Live cells without any live neighbors don't show up in the counts, and dying cells
+        // decay whatever their neighbors are
+        for (pos, state) in &self.cells {
+            let count = neighbor_counts.get(pos).copied().unwrap_or(0);
+            let next = rule.next_cell(*state, count);
+            if next != 0 {
+                cells.insert(*pos, next);
+            }
+        }
+        cells.extend(
+            neighbor_counts
+                .into_iter()
+                .filter(|(pos, count)| !self.cells.contains_key(pos) && rule.birth[*count])
+                .map(|(pos, _)| (pos, 1)),
+        );
+        self.cells = cells;
+    }
+
+    fn step_isotropic(&mut self, rule: &Isotropic) {
+        let mut neighborhoods: HashMap<Coord, u8> = HashMap::with_capacity(self.cells.len() * 8);
+        for (pos, _) in self.cells.iter().filter(|(_, state)| **state == 1) {
+            for (i, [dx, dy]) in NEIGHBOR_BITS.iter().enumerate() {
+                // The neighbor sees this cell in the opposite direction
+                *neighborhoods
+                    .entry(Coord::new(pos.x + dx, pos.y + dy))
+                    .or_insert(0) |= 1 << ((i + 4) % 8);
+            }
+        }
+        let mut cells = HashMap::with_capacity(self.cells.len());
+        for (pos, state) in &self.cells {
+            let next = rule.next_cell(*state, neighborhoods.get(pos).copied().unwrap_or(0));
+            if next != 0 {
+                cells.insert(*pos, next);
+            }
+        }
+        cells.extend(
+            neighborhoods
+                .into_iter()
+                .filter(|(pos, neighborhood)| {
+                    !self.cells.contains_key(pos) && rule.next_cell(0, *neighborhood) == 1
+                })
+                .map(|(pos, _)| (pos, 1)),
+        );
+        self.cells = cells;
+    }
+
+    /// Steps the cells and their neighbors. Dead cells without any neighbors that aren't dead
+    /// are assumed to stay dead, like in Golly.
+    fn step_table(&mut self, rule: &RuleTable) {
+        let offsets = rule.neighborhood.offsets();
+        let mut candidates: HashSet<Coord> =
+            HashSet::with_capacity(self.cells.len() * (offsets.len() + 1));
+        for pos in self.cells.keys() {
+            candidates.insert(*pos);
+            candidates.extend(
+                offsets
+                    .iter()
+                    .map(|[dx, dy]| Coord::new(pos.x + dx, pos.y + dy)),
+            );
+        }
+        let mut next_states = HashMap::new();
+        let mut cells = HashMap::with_capacity(self.cells.len());
+        let mut inputs = [0; 9];
+        for pos in candidates {
+            inputs[0] = self.get(pos);
+            for (i, [dx, dy]) in offsets.iter().enumerate() {
+                inputs[i + 1] = self.get(Coord::new(pos.x + dx, pos.y + dy));
+            }
+            let next = *next_states
+                .entry(inputs)
+                .or_insert_with(|| rule.next_cell(&inputs[..rule.inputs()]));
+            if next != 0 {
+                cells.insert(pos, next);
+            }
+        }
+        self.cells = cells;
+    }
+
+    /// Steps the tiles with a live cell within the radius of them, the only ones where cells can
+    /// be alive after the step. Cells far apart are stepped in separate tiles, so the memory
+    /// used follows the number of cells instead of the area they're spread over.
+    fn step_larger_than_life(&mut self, rule: &LargerThanLife) {
+        let radius = rule.radius as i64;
+        // At least twice the radius, so the border read around a tile is never wider than it
+        let side = (2 * radius).max(MIN_TILE_SIDE);
+        let mut tiles = HashSet::new();
+        for pos in self.cells.keys() {
+            for x in (pos.x - radius).div_euclid(side)..=(pos.x + radius).div_euclid(side) {
+                for y in (pos.y - radius).div_euclid(side)..=(pos.y + radius).div_euclid(side) {
+                    tiles.insert((x, y));
+                }
+            }
+        }
+        let mut cells = HashMap::with_capacity(self.cells.len());
+        for (x, y) in tiles {
+            let min = Coord::new(x * side, y * side);
+            let next = rule.step_block(side as usize, side as usize, |x, y| {
+                self.get(Coord::new(min.x + x, min.y + y))
+            });
+            cells.extend(
+                next.into_iter()
+                    .enumerate()
+                    .filter(|(_, state)| *state != 0)
+                    .map(|(index, state)| {
+                        let pos =
+                            Coord::new(min.x + index as i64 / side, min.y + index as i64 % side);
+                        (pos, state)
+                    }),
+            );
+        }
+        self.cells = cells;
+    }
+}
@@ -0,0 +1,138 @@
+use std::error::Error;
+use std::str::FromStr;
+
+use game_of_life::rule::Rule;
+use game_of_life::state::{BoundingBox, Coord, GridSize, StateGrid};
+use game_of_life::Universe;
+
+use crate::args::Options;
+use crate::config::Config;
+use crate::files::{apply_rulestring, open_pattern, pattern_dir};
+use crate::grid::ViewOrigin;
+
+/// The engines the command line can pick, see `StateGrid`
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Engine {
+    Bounded,
+    Unbounded,
+    HashLife,
+}
+
+impl Engine {
+    fn runs(self, grid: &StateGrid) -> bool {
+        matches!(
+            (self, grid),
+            (Self::Bounded, StateGrid::Bounded(_))
+                | (Self::Unbounded, StateGrid::Unbounded(_))
+                | (Self::HashLife, StateGrid::HashLife(_))
+        )
+    }
+}
+
+impl FromStr for Engine {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "bounded" => Ok(Self::Bounded),
+            "unbounded" => Ok(Self::Unbounded),
+            "hashlife" => Ok(Self::HashLife),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Rules with B0 would fill the unbounded plane at once, so only the bounded grid runs them
+pub fn check_unbounded(rule: &Rule) -> Result<(), String> {
+    if rule.births_in_empty_space() {
+        return Err(format!(
+            "{} has B0, only the bounded engine can run it",
+            rule
+        ));
+    }
+    Ok(())
+}
+
+/// Cycles through the engines until it reaches `engine`
+fn switch_engine(universe: &mut Universe, engine: Engine, size: GridSize) -> Result<(), String> {
+    if engine == Engine::HashLife && universe.rule().transition_table().is_none() {
+        return Err(format!("HashLife can't run {}", universe.rule()));
+    }
+    for _ in 0..3 {
+        if engine.runs(universe.grid()) {
+            return Ok(());
+        }
+        universe.next_engine(size);
+    }
+    unreachable!()
+}
+
+/// The simulation as the command line sets it up, before the app or a headless run starts
+pub struct Startup {
+    pub universe: Universe,
+    pub grid_size: GridSize,
+    pub view_origin: ViewOrigin,
+}
+
+impl Startup {
+    /// Opens the pattern, then applies the rule, size and topology options, which win over the
+    /// ones of the pattern, and fills the board with a soup for a seed. The rule and grid size of
+    /// the config are used unless the pattern or the options set them. `engine` is used unless the
+    /// options or the pattern pick another one.
+    pub fn new(options: &Options, config: &Config, engine: Engine) -> Result<Self, Box<dyn Error>> {
+        let mut grid_size = options.size.unwrap_or_else(|| config.grid.into());
+        let mut view_origin = ViewOrigin::default();
+        let mut universe = Universe::new(grid_size);
+        universe.set_rule(config.rule.clone());
+        let engine = options.engine.unwrap_or(engine);
+        // Patterns load into the engine before they are stepped, so the bounded grid can't cut off
+        // large patterns. HashLife would lose the dying cells, the hash set engine keeps them.
+        let loading_engine = match engine {
+            Engine::HashLife => Engine::Unbounded,
+            engine => engine,
+        };
+        switch_engine(&mut universe, loading_engine, grid_size)?;
+        if let Some(path) = &options.pattern {
+            open_pattern(path, &mut view_origin, &mut universe, &mut grid_size)?;
+        }
+        if let Some(rulestring) = &options.rule {
+            let pattern_dir = options.pattern.as_deref().map_or(".".as_ref(), pattern_dir);
+            apply_rulestring(rulestring, pattern_dir, &mut universe, &mut grid_size)?;
+        }
+        if let Some(size) = options.size {
+            grid_size = size;
+        }
+        universe.resize(grid_size);
+        if let Some(topology) = options.topology {
+            universe.set_topology(topology);
+        }
+        if let Some(seed) = options.seed {
+            let origin = view_origin.0;
+            let area = BoundingBox {
+                min: origin,
+                max: Coord::new(
+                    origin.x + grid_size.width as i64 - 1,
+                    origin.y + grid_size.height as i64 - 1,
+                ),
+            };
+            universe.randomize(area, seed);
+        }
+        // Patterns on a bounded grid and macrocell patterns bring their own engine, unless
+        // another one was asked for. Rules HashLife can't run stay on the hash set engine.
+        let chosen_by_pattern = !loading_engine.runs(universe.grid());
+        let unsupported =
+            engine == Engine::HashLife && universe.rule().transition_table().is_none();
+        if options.engine.is_some() || !(chosen_by_pattern || unsupported) {
+            switch_engine(&mut universe, engine, grid_size)?;
+        }
+        // The rule of the pattern or the options may have B0, which the unbounded engines can't run
+        if !matches!(universe.grid(), StateGrid::Bounded(_)) {
+            check_unbounded(universe.rule())?;
+        }
+        Ok(Self {
+            universe,
+            grid_size,
+            view_origin,
+        })
+    }
+}
@@ -0,0 +1,492 @@
+use std::collections::HashMap;
+
+use crate::bitgrid::BitGrid;
+use crate::executor::Executor;
+use crate::hashlife::HashLife;
+use crate::isotropic::{self, Isotropic};
+use crate::rule::{LifeLike, Rule};
+use crate::ruletable::RuleTable;
+use crate::sparse::SparseGrid;
+use crate::topology::Topology;
+
+pub const NEIGHBORS: [[i64; 2]; 8] = [
+    [-1, -1],
+    [0, -1],
+    [1, -1],
+    [-1, 0],
+    [1, 0],
+    [-1, 1],
+    [0, 1],
+    [1, 1],
+];
+
+/// The neighbors on a hexagonal grid sheared onto the square one, like Golly stores them: the
+/// north-east and south-west cells aren't neighbors
+pub const HEXAGONAL_NEIGHBORS: [[i64; 2]; 6] = [[0, -1], [1, -1], [-1, 0], [1, 0], [-1, 1], [0, 1]];
+
+pub const VON_NEUMANN_NEIGHBORS: [[i64; 2]; 4] = [[0, -1], [-1, 0], [1, 0], [0, 1]];
+
+/// The number of cells along each axis of the board
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct GridSize {
+    pub width: usize,
+    pub height: usize,
+}
+
+impl Default for GridSize {
+    fn default() -> Self {
+        Self {
+            width: 50,
+            height: 50,
+        }
+    }
+}
+
+/// The coordinates of a cell. Bounded grids only use the range `0..width` and `0..height`.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub struct Coord {
+    pub x: i64,
+    pub y: i64,
+}
+
+impl Coord {
+    pub fn new(x: i64, y: i64) -> Self {
+        Self { x, y }
+    }
+}
+
+/// The smallest rectangle containing a set of cells, both corners included
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct BoundingBox {
+    pub min: Coord,
+    pub max: Coord,
+}
+
+impl BoundingBox {
+    pub fn from_cells(cells: impl IntoIterator<Item = Coord>) -> Option<Self> {
+        let mut cells = cells.into_iter();
+        let first = cells.next()?;
+        Some(cells.fold(
+            Self {
+                min: first,
+                max: first,
+            },
+            Self::including,
+        ))
+    }
+
+    /// Grows the box to contain `pos`
+    pub fn including(self, pos: Coord) -> Self {
+        Self {
+            min: Coord::new(self.min.x.min(pos.x), self.min.y.min(pos.y)),
+            max: Coord::new(self.max.x.max(pos.x), self.max.y.max(pos.y)),
+        }
+    }
+
+    pub fn contains(&self, pos: Coord) -> bool {
+        (self.min.x..=self.max.x).contains(&pos.x) && (self.min.y..=self.max.y).contains(&pos.y)
+    }
+
+    pub fn width(&self) -> u64 {
+        self.max.x.abs_diff(self.min.x) + 1
+    }
+
+    pub fn height(&self) -> u64 {
+        self.max.y.abs_diff(self.min.y) + 1
+    }
+}
+
+/// The cells of the simulation, stored either as a fixed size grid or as an unbounded plane.
+/// Each kind of storage comes with its own simulation engine.
+pub enum StateGrid {
+    Bounded(DenseGrid),
+    Unbounded(SparseGrid),
+    HashLife(HashLife),
+}
+
+impl StateGrid {
+    /// The state of the cell, 0 if it is dead
+    pub fn get(&self, pos: Coord) -> u8 {
+        match self {
+            Self::Bounded(grid) => grid.get(pos),
+            Self::Unbounded(grid) => grid.get(pos),
+            Self::HashLife(grid) => grid.get(pos) as u8,
+        }
+    }
+
+    /// HashLife only stores whether cells are alive, it keeps every state above 0 as alive
+    pub fn set(&mut self, pos: Coord, state: u8) {
+        match self {
+            Self::Bounded(grid) => grid.set(pos, state),
+            Self::Unbounded(grid) => grid.set(pos, state),
+            Self::HashLife(grid) => grid.set(pos, state != 0),
+        }
+    }
+
+    /// Sets a cell to `state`, or kills it if it already is in that state
+    pub fn toggle(&mut self, pos: Coord, state: u8) {
+        self.set(pos, if self.get(pos) == state { 0 } else { state });
+    }
+
+    /// Advances the simulation by 2^`step_log2` generations. Only HashLife can skip generations,
+    /// the other engines compute every one of them. The topology only applies to bounded grids.
+    /// HashLife is replaced by the hash set engine for rules it can't run. Rules with B0 only run
+    /// on bounded grids, the unbounded engines leave the cells and the generation as they are.
+    /// Bounded grids step bands of rows in parallel on the executor.
+    pub fn step_pow2(
+        &mut self,
+        rule: &Rule,
+        topology: Topology,
+        step_log2: u32,
+        executor: &dyn Executor,
+    ) {
+        if !matches!(self, Self::Bounded(_)) && rule.births_in_empty_space() {
+            return;
+        }
+        let table = rule.transition_table();
+        if matches!(self, Self::HashLife(_)) && table.is_none() {
+            self.replace_engine(Self::Unbounded(SparseGrid::default()));
+        }
+        match self {
+            Self::Bounded(grid) => {
+                (0..1u64 << step_log2).for_each(|_| grid.step(rule, topology, executor))
+            }
+            Self::Unbounded(grid) => (0..1u64 << step_log2).for_each(|_| grid.step(rule)),
+            Self::HashLife(grid) => {
+                if let Some(table) = table {
+                    grid.step_pow2(&table, step_log2);
+                }
+            }
+        }
+    }
+
+    /// The live and dying cells
+    pub fn live_cells(&self) -> Vec<Coord> {
+        match self {
+            Self::Bounded(grid) => grid.cells().map(|(pos, _)| pos).collect(),
+            Self::Unbounded(grid) => grid.cells().map(|(pos, _)| pos).collect(),
+            Self::HashLife(grid) => grid.live_cells(),
+        }
+    }
+
+    /// The live and dying cells with their states
+    pub fn cells(&self) -> Vec<(Coord, u8)> {
+        match self {
+            Self::Bounded(grid) => grid.cells().collect(),
+            Self::Unbounded(grid) => grid.cells().collect(),
+            Self::HashLife(grid) => grid.live_cells().into_iter().map(|pos| (pos, 1)).collect(),
+        }
+    }
+
+    /// The live and dying cells inside of `area` with their states
+    pub fn cells_in(&self, area: BoundingBox) -> Vec<(Coord, u8)> {
+        match self {
+            Self::Bounded(grid) => grid
+                .cells()
+                .filter(|(pos, _)| area.contains(*pos))
+                .collect(),
+            Self::Unbounded(grid) => grid
+                .cells()
+                .filter(|(pos, _)| area.contains(*pos))
+                .collect(),
+            Self::HashLife(grid) => grid
+                .live_cells_in(area)
+                .into_iter()
+                .map(|pos| (pos, 1))
+                .collect(),
+        }
+    }
+
+    /// The number of live and dying cells
+    pub fn population(&self) -> u64 {
+        match self {
+            Self::Bounded(grid) => grid.population(),
+            Self::Unbounded(grid) => grid.population(),
+            Self::HashLife(grid) => grid.population(),
+        }
+    }
+
+    pub fn bounding_box(&self) -> Option<BoundingBox> {
+        match self {
+            Self::Bounded(grid) => BoundingBox::from_cells(grid.cells().map(|(pos, _)| pos)),
+            Self::Unbounded(grid) => BoundingBox::from_cells(grid.cells().map(|(pos, _)| pos)),
+            Self::HashLife(grid) => grid.bounding_box(),
+        }
+    }
+
+    pub fn generation(&self) -> u64 {
+        match self {
+            Self::Bounded(grid) => grid.generation,
+            Self::Unbounded(grid) => grid.generation,
+            Self::HashLife(grid) => grid.generation(),
+        }
+    }
+
+    pub fn set_generation(&mut self, generation: u64) {
+        match self {
+            Self::Bounded(grid) => grid.generation = generation,
+            Self::Unbounded(grid) => grid.generation = generation,
+            Self::HashLife(grid) => grid.set_generation(generation),
+        }
+    }
+
+    /// Kills every cell and resets the generation, keeping the storage kind and size
+    pub fn clear(&mut self) {
+        match self {
+            Self::Bounded(grid) => *grid = DenseGrid::new(grid.size()),
+            Self::Unbounded(grid) => *grid = SparseGrid::default(),
+            Self::HashLife(grid) => *grid = HashLife::default(),
+        }
+    }
+
+    /// Resizes a bounded grid, unbounded grids are left as they are
+    pub fn resize(&mut self, size: GridSize) {
+        if let Self::Bounded(grid) = self {
+            *grid = grid.resized(size);
+        }
+    }
+
+    /// Switches to the next kind of storage, cycling through the bounded grid, the hash set and
+    /// HashLife. HashLife is skipped for rules it can't run, rules with B0 stay on the bounded
+    /// grid. Live cells outside of `size` are lost when switching to a bounded grid.
+    pub fn next_engine(&mut self, size: GridSize, rule: &Rule) {
+        let grid = match self {
+            Self::Bounded(_) if rule.births_in_empty_space() => return,
+            Self::Bounded(_) => Self::Unbounded(SparseGrid::default()),
+            Self::Unbounded(_) if rule.transition_table().is_some() => {
+                Self::HashLife(HashLife::default())
+            }
+            Self::Unbounded(_) | Self::HashLife(_) => Self::Bounded(DenseGrid::new(size)),
+        };
+        self.replace_engine(grid);
+    }
+
+    /// Moves the cells and the generation over to `grid`, an empty grid of another kind
+    fn replace_engine(&mut self, mut grid: StateGrid) {
+        for (pos, state) in self.cells() {
+            grid.set(pos, state);
+        }
+        grid.set_generation(self.generation());
+        *self = grid;
+    }
+}
+
+/// A fixed size grid whose edges are joined according to a `Topology`
+pub struct DenseGrid {
+    cells: DenseCells,
+    pub generation: u64,
+}
+
+/// The cells of a dense grid. Two state rules step cells packed into bits, other rules and
+/// dying cells need a byte per cell. The grid switches between them as needed.
+enum DenseCells {
+    Bytes(Vec<Vec<u8>>),
+    Bits(BitGrid),
+}
+
+impl DenseGrid {
+    pub fn new(size: GridSize) -> Self {
+        Self {
+            cells: DenseCells::Bits(BitGrid::new(size.width, size.height)),
+            generation: 0,
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        match &self.cells {
+            DenseCells::Bytes(cells) => cells.len(),
+            DenseCells::Bits(cells) => cells.width(),
+        }
+    }
+
+    pub fn height(&self) -> usize {
+        match &self.cells {
+            DenseCells::Bytes(cells) => cells.first().map_or(0, Vec::len),
+            DenseCells::Bits(cells) => cells.height(),
+        }
+    }
+
+    pub fn size(&self) -> GridSize {
+        GridSize {
+            width: self.width(),
+            height: self.height(),
+        }
+    }
+
+    fn contains(&self, pos: Coord) -> bool {
+        (0..self.width() as i64).contains(&pos.x) && (0..self.height() as i64).contains(&pos.y)
+    }
+
+    /// Cells outside the grid are always dead
+    pub fn get(&self, pos: Coord) -> u8 {
+        if !self.contains(pos) {
+            return 0;
+        }
+        let (x, y) = (pos.x as usize, pos.y as usize);
+        match &self.cells {
+            DenseCells::Bytes(cells) => cells[x][y],
+            DenseCells::Bits(cells) => cells.get(x, y) as u8,
+        }
+    }
+
+    /// Setting cells outside the grid does nothing
+    pub fn set(&mut self, pos: Coord, state: u8) {
+        if !self.contains(pos) {
+            return;
+        }
+        let (x, y) = (pos.x as usize, pos.y as usize);
+        match &mut self.cells {
+            DenseCells::Bits(cells) if state <= 1 => cells.set(x, y, state == 1),
+            _ => self.bytes_mut()[x][y] = state,
+        }
+    }
+
+    /// The cells with a byte per cell, unpacking them if they are packed into bits
+    fn bytes_mut(&mut self) -> &mut Vec<Vec<u8>> {
+        if let DenseCells::Bits(bits) = &self.cells {
+            let mut cells = vec![vec![0; bits.height()]; bits.width()];
+            for (x, y) in bits.live_cells() {
+                cells[x][y] = 1;
+            }
+            self.cells = DenseCells::Bytes(cells);
+        }
+        match &mut self.cells {
+            DenseCells::Bytes(cells) => cells,
+            DenseCells::Bits(_) => unreachable!(),
+        }
+    }
+
+    /// The cells packed into bits, unless some of them are dying
+    fn bits_mut(&mut self) -> Option<&mut BitGrid> {
+        if let DenseCells::Bytes(cells) = &self.cells {
+            if cells.iter().flatten().any(|state| *state > 1) {
+                return None;
+            }
+            let mut bits = BitGrid::new(self.width(), self.height());
+            for (pos, _) in self.cells() {
+                bits.set(pos.x as usize, pos.y as usize, true);
+            }
+            self.cells = DenseCells::Bits(bits);
+        }
+        match &mut self.cells {
+            DenseCells::Bits(bits) => Some(bits),
+            DenseCells::Bytes(_) => unreachable!(),
+        }
+    }
+
+    /// Returns a grid of the given size keeping the cells that lie inside both grids
+    pub fn resized(&self, size: GridSize) -> Self {
+        let mut grid = Self::new(size);
+        for (pos, state) in self.cells() {
+            grid.set(pos, state);
+        }
+        grid.generation = self.generation;
+        grid
+    }
+
+    pub fn population(&self) -> u64 {
+        match &self.cells {
+            DenseCells::Bytes(_) => self.cells().count() as u64,
+            DenseCells::Bits(cells) => cells.population(),
+        }
+    }
+
+    /// The live and dying cells with their states
+    pub fn cells(&self) -> Box<dyn Iterator<Item = (Coord, u8)> + '_> {
+        match &self.cells {
+            DenseCells::Bytes(cells) => {
+                Box::new(cells.iter().enumerate().flat_map(|(x, column)| {
+                    column
+                        .iter()
+                        .enumerate()
+                        .filter(|(_, state)| **state != 0)
+                        .map(move |(y, state)| (Coord::new(x as i64, y as i64), *state))
+                }))
+            }
+            DenseCells::Bits(cells) => Box::new(
+                cells
+                    .live_cells()
+                    .map(|(x, y)| (Coord::new(x as i64, y as i64), 1)),
+            ),
+        }
+    }
+
+    pub fn step(&mut self, rule: &Rule, topology: Topology, executor: &dyn Executor) {
+        match rule {
+            Rule::LifeLike(rule) if !rule.is_generations() => match self.bits_mut() {
+                Some(bits) => bits.step_parallel(rule, topology, executor),
+                None => step_life_like(self.bytes_mut(), rule, topology),
+            },
+            Rule::LifeLike(rule) => step_life_like(self.bytes_mut(), rule, topology),
+            Rule::Isotropic(rule) => step_isotropic(self.bytes_mut(), rule, topology),
+            Rule::Table(rule) => step_table(self.bytes_mut(), rule, topology),
+            Rule::LargerThanLife(rule) => {
+                let cells = self.bytes_mut();
+                let (width, height) = (cells.len(), cells[0].len());
+                let next = rule.step_block(width, height, |x, y| {
+                    topology
+                        .wrap(x, y, width, height)
+                        .map_or(0, |(x, y)| cells[x][y])
+                });
+                for (x, column) in cells.iter_mut().enumerate() {
+                    column.copy_from_slice(&next[x * height..(x + 1) * height]);
+                }
+            }
+        }
+        self.generation += 1;
+    }
+}
+
+/// Steps cells stored a byte per cell, which also covers dying states
+pub fn step_life_like(cells: &mut [Vec<u8>], rule: &LifeLike, topology: Topology) {
+    let initial_state_grid = cells.to_vec();
+    let (width, height) = (cells.len(), cells[0].len());
+    for x in 0..width {
+        for y in 0..height {
+            let mut num_alive_nb = 0;
+            for [dx, dy] in rule.neighbors.offsets() {
+                let neighbor = topology.wrap(x as i64 + dx, y as i64 + dy, width, height);
+                if neighbor.is_some_and(|(nx, ny)| initial_state_grid[nx][ny] == 1) {
+                    num_alive_nb += 1;
+                }
+            }
+            cells[x][y] = rule.next_cell(initial_state_grid[x][y], num_alive_nb);
+        }
+    }
+}
+
+fn step_isotropic(cells: &mut [Vec<u8>], rule: &Isotropic, topology: Topology) {
+    let initial_state_grid = cells.to_vec();
+    let (width, height) = (cells.len(), cells[0].len());
+    for x in 0..width {
+        for y in 0..height {
+            let neighborhood = isotropic::neighborhood(|dx, dy| {
+                topology
+                    .wrap(x as i64 + dx, y as i64 + dy, width, height)
+                    .is_some_and(|(nx, ny)| initial_state_grid[nx][ny] == 1)
+            });
+            cells[x][y] = rule.next_cell(initial_state_grid[x][y], neighborhood);
+        }
+    }
+}
+
+fn step_table(cells: &mut [Vec<u8>], rule: &RuleTable, topology: Topology) {
+    let initial_state_grid = cells.to_vec();
+    let (width, height) = (cells.len(), cells[0].len());
+    // Most cells look alike, so each arrangement is only matched against the table once
+    let mut next_states = HashMap::new();
+    let mut inputs = [0; 9];
+    for x in 0..width {
+        for y in 0..height {
+            inputs[0] = initial_state_grid[x][y];
+            for (i, [dx, dy]) in rule.neighborhood.offsets().iter().enumerate() {
+                inputs[i + 1] = topology
+                    .wrap(x as i64 + dx, y as i64 + dy, width, height)
+                    .map_or(0, |(nx, ny)| initial_state_grid[nx][ny]);
+            }
+            cells[x][y] = *next_states
+                .entry(inputs)
+                .or_insert_with(|| rule.next_cell(&inputs[..rule.inputs()]));
+        }
+    }
+}
@@ -0,0 +1,151 @@
+use bevy::prelude::*;
+use game_of_life::state::{BoundingBox, Coord, StateGrid};
+use game_of_life::Universe;
+
+use crate::{NumberInput, StepExponent};
+
+/// Births and deaths are only counted up to this population, since counting them needs a copy of
+/// every live cell from before the step
+const MAX_COUNTED_POPULATION: u64 = 1 << 20;
+
+/// Keeps the `SimulationStats` up to date and shows them in the top left corner of the window
+pub struct StatsPlugin;
+
+impl Plugin for StatsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SimulationStats>()
+            .add_startup_system(spawn_hud)
+            .add_system_set_to_stage(
+                CoreStage::PostUpdate,
+                SystemSet::new()
+                    .with_system(refresh_stats.label("refresh_stats"))
+                    .with_system(update_hud.after("refresh_stats")),
+            );
+    }
+}
+
+#[derive(Default)]
+pub struct SimulationStats {
+    pub generation: u64,
+    pub population: u64,
+    /// Cells that were dead before the last step and alive after it, if the population was small
+    /// enough to count them
+    pub births: Option<u64>,
+    /// Cells that were alive or dying before the last step and dead after it
+    pub deaths: Option<u64>,
+    pub bounding_box: Option<BoundingBox>,
+}
+
+impl SimulationStats {
+    /// Whether the next step's births and deaths are counted, which needs the live cells from
+    /// before it passed to `record_step`
+    pub fn counts_step(state_grid: &StateGrid) -> bool {
+        state_grid.population() <= MAX_COUNTED_POPULATION
+    }
+
+    pub fn record_step(&mut self, cells_before: Option<&[(Coord, u8)]>, state_grid: &StateGrid) {
+        self.refresh(state_grid);
+        match cells_before {
+            Some(before) => {
+                let survivors = before
+                    .iter()
+                    .filter(|(pos, _)| state_grid.get(*pos) != 0)
+                    .count() as u64;
+                self.births = Some(self.population - survivors);
+                self.deaths = Some(before.len() as u64 - survivors);
+            }
+            _ => {
+                self.births = None;
+                self.deaths = None;
+            }
+        }
+    }
+
+    /// Updates everything but the births and deaths, which only change with a step
+    pub fn refresh(&mut self, state_grid: &StateGrid) {
+        self.generation = state_grid.generation();
+        self.population = state_grid.population();
+        self.bounding_box = state_grid.bounding_box();
+    }
+}
+
+#[derive(Component)]
+struct HudText;
+
+fn spawn_hud(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.spawn_bundle(UiCameraBundle::default());
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    top: Val::Px(5.0),
+                    left: Val::Px(5.0),
+                    ..default()
+                },
+                padding: Rect::all(Val::Px(4.0)),
+                ..default()
+            },
+            color: UiColor(Color::rgba(0.0, 0.0, 0.0, 0.6)),
+            ..default()
+        })
+        .with_children(|parent| {
+            parent
+                .spawn_bundle(TextBundle {
+                    text: Text::with_section(
+                        "",
+                        TextStyle {
+                            font: asset_server.load("fonts/FiraMono-Medium.ttf"),
+                            font_size: 14.0,
+                            color: Color::rgb(1.0, 0.8, 0.2),
+                        },
+                        default(),
+                    ),
+                    ..default()
+                })
+                .insert(HudText);
+        });
+}
+
+/// Refreshes the stats after edits like drawing, clearing or loading, steps refresh them in
+/// `update_cells`
+fn refresh_stats(universe: Res<Universe>, mut stats: ResMut<SimulationStats>) {
+    if universe.is_changed() && !stats.is_changed() {
+        stats.refresh(universe.grid());
+    }
+}
+
+fn update_hud(
+    stats: Res<SimulationStats>,
+    step_exponent: Res<StepExponent>,
+    number_input: Res<NumberInput>,
+    mut hud: Query<&mut Text, With<HudText>>,
+) {
+    if !stats.is_changed() && !step_exponent.is_changed() && !number_input.is_changed() {
+        return;
+    }
+    let count = |value: Option<u64>| value.map_or("-".to_string(), |value| value.to_string());
+    let mut text = format!(
+        "Generation {}\nPopulation {}\nBirths {} Deaths {}\n",
+        stats.generation,
+        stats.population,
+        count(stats.births),
+        count(stats.deaths),
+    );
+    if let Some(bounding_box) = stats.bounding_box {
+        text.push_str(&format!(
+            "Bounds {}x{} from ({}, {})\n",
+            bounding_box.width(),
+            bounding_box.height(),
+            bounding_box.min.x,
+            bounding_box.min.y,
+        ));
+    }
+    text.push_str(&format!("Step 2^{}", step_exponent.0));
+    if let Some(number) = number_input.0 {
+        text.push_str(&format!("\nInput {}", number));
+    }
+    for mut hud_text in hud.iter_mut() {
+        hud_text.sections[0].value = text.clone();
+    }
+}
@@ -0,0 +1,167 @@
+use bevy::prelude::*;
+use bevy::render::render_resource::{Extent3d, FilterMode, TextureDimension, TextureFormat};
+use game_of_life::state::GridSize;
+use game_of_life::Universe;
+
+use crate::controls::{Action, Controls};
+use crate::grid::{
+    cell_color, cell_corner, row_offset, BoardView, CellColors, RenderMode, Size, VisibleCells,
+};
+
+/// Draws the visible cells as a single sprite whose texture has one pixel per cell, used in
+/// `RenderMode::Texture`. Hexagonal cells are two pixels wide and every row is shifted one pixel
+/// further right than the one below it, like the sprites are.
+pub struct BoardTexturePlugin;
+
+impl Plugin for BoardTexturePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(toggle_render_mode)
+            .add_system(spawn_board_texture.after("render_mode"))
+            .add_system_to_stage(CoreStage::PostUpdate, update_board_texture);
+    }
+}
+
+#[derive(Component)]
+struct BoardTexture;
+
+/// Switches between drawing sprites and drawing the texture
+fn toggle_render_mode(controls: Controls, mut render_mode: ResMut<RenderMode>) {
+    if controls.just_released(Action::ToggleRenderMode) {
+        *render_mode = match *render_mode {
+            RenderMode::Sprites => RenderMode::Texture,
+            RenderMode::Texture => RenderMode::Sprites,
+        };
+    }
+}
+
+/// The width of a cell in pixels, and how many pixels wide the texture of the cells is
+fn texture_width(size: GridSize, hexagonal: bool) -> (usize, usize) {
+    if hexagonal {
+        (2, 2 * size.width + size.height - 1)
+    } else {
+        (1, size.width)
+    }
+}
+
+/// Replaces the board sprite whenever the render mode or the size of its texture changes, which
+/// the number of visible cells and the shape of the cells decide
+fn spawn_board_texture(
+    mut commands: Commands,
+    visible: Res<VisibleCells>,
+    render_mode: Res<RenderMode>,
+    universe: Res<Universe>,
+    mut texture_size: Local<Option<(usize, usize)>>,
+    mut images: ResMut<Assets<Image>>,
+    boards: Query<Entity, With<BoardTexture>>,
+) {
+    let size = visible.size();
+    let (cell_width, width) = texture_width(size, universe.rule().is_hexagonal());
+    if !render_mode.is_changed() && *texture_size == Some((width, size.height)) {
+        return;
+    }
+    *texture_size = Some((width, size.height));
+    for entity in boards.iter() {
+        commands.entity(entity).despawn();
+    }
+    if *render_mode != RenderMode::Texture {
+        return;
+    }
+    let mut image = Image::new_fill(
+        Extent3d {
+            width: width as u32,
+            height: size.height as u32,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        &[0, 0, 0, 255],
+        TextureFormat::Rgba8UnormSrgb,
+    );
+    // Keep the cells sharp instead of blurring them together
+    image.sampler_descriptor.mag_filter = FilterMode::Nearest;
+    image.sampler_descriptor.min_filter = FilterMode::Nearest;
+    commands
+        .spawn_bundle(SpriteBundle {
+            texture: images.add(image),
+            sprite: Sprite {
+                custom_size: Some(Vec2::ONE),
+                ..default()
+            },
+            ..default()
+        })
+        .insert(BoardTexture)
+        .insert(Size::new(
+            width as f32 / cell_width as f32,
+            size.height as f32,
+        ));
+}
+
+fn pixel(color: Color) -> [u8; 4] {
+    color
+        .as_rgba_f32()
+        .map(|component| (component * 255.0).round() as u8)
+}
+
+/// Places the texture over the visible cells, and redraws it when the cells, their colors or the
+/// visible part of the plane changed
+fn update_board_texture(
+    view: BoardView,
+    universe: Res<Universe>,
+    colors: Res<CellColors>,
+    mut boards: Query<(&Handle<Image>, &mut Transform, ChangeTrackers<BoardTexture>)>,
+    mut images: ResMut<Assets<Image>>,
+) {
+    for (handle, mut transform, tracker) in boards.iter_mut() {
+        let rule = universe.rule();
+        let hexagonal = rule.is_hexagonal();
+        let (visible, grid_size) = (*view.visible, *view.grid_size);
+        let size = visible.size();
+        let (cell_width, texture_width) = texture_width(size, hexagonal);
+        let image = images.get_mut(handle).unwrap();
+        if image.texture_descriptor.size.width as usize != texture_width
+            || image.texture_descriptor.size.height as usize != size.height
+        {
+            // The sprite is replaced with one of the new size
+            continue;
+        }
+        // The bottom row is the one shifted furthest left
+        let bottom_left = visible.position(*view.origin, 0, 0);
+        let cell_size = view.cell_size();
+        let offset = row_offset(bottom_left.y, grid_size.height, hexagonal);
+        let corner = cell_corner(
+            bottom_left.x as f32 + offset,
+            bottom_left.y as f32,
+            grid_size,
+            cell_size,
+        );
+        let texture_size = Vec2::new(texture_width as f32 / cell_width as f32, size.height as f32);
+        transform.translation = (corner + texture_size * cell_size / 2.0).extend(0.0);
+        if !universe.is_changed() && !view.visible.is_changed() && !tracker.is_added() {
+            continue;
+        }
+        let (width, height) = (size.width as i64, size.height as i64);
+        // The pixel of the bottom left corner of a cell, counting rows from the top
+        let pixel_index = |x: i64, y: i64| {
+            let shift = if hexagonal { y } else { 0 };
+            (((height - 1 - y) * texture_width as i64 + x * cell_width as i64 + shift) * 4) as usize
+        };
+        // Around hexagonal cells the texture is transparent
+        image.data.fill(0);
+        let dead_pixel = pixel(cell_color(0, rule, &colors));
+        for y in 0..height {
+            let start = pixel_index(0, y);
+            for pixel in
+                image.data[start..start + width as usize * cell_width * 4].chunks_exact_mut(4)
+            {
+                pixel.copy_from_slice(&dead_pixel);
+            }
+        }
+        let min = visible.0.min;
+        for (pos, state) in universe.cells_in(visible.0) {
+            let index = pixel_index(pos.x - min.x, pos.y - min.y);
+            let color = pixel(cell_color(state, rule, &colors));
+            for pixel in image.data[index..index + cell_width * 4].chunks_exact_mut(4) {
+                pixel.copy_from_slice(&color);
+            }
+        }
+    }
+}
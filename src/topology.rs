@@ -0,0 +1,245 @@
+use std::fmt;
+use std::str::FromStr;
+
+use modulo::Mod;
+
+use crate::state::GridSize;
+
+/// How the edges of the bounded grid are joined
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Topology {
+    /// Opposite edges are joined, so patterns wrap around both axes
+    Torus,
+    /// No edges are joined, cells outside of the grid are dead
+    Plane,
+    /// Only the left and right edges are joined
+    VerticalCylinder,
+    /// Only the top and bottom edges are joined
+    HorizontalCylinder,
+    /// Opposite edges are joined, one pair of them with a twist: cells leaving through a twisted
+    /// edge come back mirrored. With `twisted_x` the top and bottom edges are twisted and mirror
+    /// the x coordinate, otherwise the left and right edges mirror the y coordinate.
+    KleinBottle { twisted_x: bool },
+    /// Both pairs of opposite edges are joined with a twist
+    CrossSurface,
+}
+
+impl Topology {
+    const ALL: [Topology; 7] = [
+        Self::Torus,
+        Self::Plane,
+        Self::VerticalCylinder,
+        Self::HorizontalCylinder,
+        Self::KleinBottle { twisted_x: true },
+        Self::KleinBottle { twisted_x: false },
+        Self::CrossSurface,
+    ];
+
+    /// The topology after this one, cycling through all of them
+    pub fn next(self) -> Self {
+        let index = Self::ALL.iter().position(|t| *t == self).unwrap_or(0);
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+
+    fn joins_left_and_right(self) -> bool {
+        !matches!(self, Self::Plane | Self::HorizontalCylinder)
+    }
+
+    fn joins_top_and_bottom(self) -> bool {
+        !matches!(self, Self::Plane | Self::VerticalCylinder)
+    }
+
+    fn twists_left_and_right(self) -> bool {
+        matches!(
+            self,
+            Self::KleinBottle { twisted_x: false } | Self::CrossSurface
+        )
+    }
+
+    fn twists_top_and_bottom(self) -> bool {
+        matches!(
+            self,
+            Self::KleinBottle { twisted_x: true } | Self::CrossSurface
+        )
+    }
+
+    /// Maps a position next to a grid of the given size onto the cell it stands for, or `None`
+    /// if it lies past an edge that isn't joined
+    pub fn wrap(self, x: i64, y: i64, width: usize, height: usize) -> Option<(usize, usize)> {
+        let (width, height) = (width as i64, height as i64);
+        let (mut x, mut y) = (x, y);
+        if !(0..width).contains(&x) {
+            if !self.joins_left_and_right() {
+                return None;
+            }
+            x = x.modulo(width);
+            if self.twists_left_and_right() {
+                y = height - 1 - y;
+            }
+        }
+        if !(0..height).contains(&y) {
+            if !self.joins_top_and_bottom() {
+                return None;
+            }
+            y = y.modulo(height);
+            if self.twists_top_and_bottom() {
+                x = width - 1 - x;
+            }
+        }
+        Some((x as usize, y as usize))
+    }
+}
+
+impl fmt::Display for Topology {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Torus => write!(f, "torus"),
+            Self::Plane => write!(f, "plane"),
+            Self::VerticalCylinder => write!(f, "vertical cylinder"),
+            Self::HorizontalCylinder => write!(f, "horizontal cylinder"),
+            Self::KleinBottle { twisted_x: true } => {
+                write!(f, "Klein bottle, twisted top and bottom")
+            }
+            Self::KleinBottle { twisted_x: false } => {
+                write!(f, "Klein bottle, twisted left and right")
+            }
+            Self::CrossSurface => write!(f, "cross-surface"),
+        }
+    }
+}
+
+/// Splits a rulestring like "B3/S23:T50,50" into the rule and Golly's bounded grid suffix
+pub fn split_rulestring(rulestring: &str) -> (&str, Option<&str>) {
+    match rulestring.split_once(':') {
+        Some((rule, bounded_grid)) => (rule, Some(bounded_grid)),
+        None => (rulestring, None),
+    }
+}
+
+/// A bounded grid in Golly's notation, the part after the ':' of "B3/S23:T50,50". A size of 0
+/// makes the grid infinite along that axis in Golly. Boards here are always finite, so that
+/// axis keeps its current size and isn't joined.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct BoundedGrid {
+    pub topology: Topology,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl BoundedGrid {
+    /// The size of the board, using `current` along infinite axes
+    pub fn size(&self, current: GridSize) -> GridSize {
+        GridSize {
+            width: if self.width == 0 {
+                current.width
+            } else {
+                self.width
+            },
+            height: if self.height == 0 {
+                current.height
+            } else {
+                self.height
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BoundedGridParseError {
+    Empty,
+    UnknownTopology(char),
+    InvalidSize(String),
+    MisplacedTwist,
+}
+
+impl fmt::Display for BoundedGridParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Empty => write!(f, "the bounded grid is empty"),
+            Self::UnknownTopology(c) => {
+                write!(f, "unknown topology '{}', expected 'T', 'P', 'K' or 'C'", c)
+            }
+            Self::InvalidSize(size) => write!(f, "'{}' is not a valid grid size", size),
+            Self::MisplacedTwist => write!(f, "only one size of a Klein bottle can have a '*'"),
+        }
+    }
+}
+
+impl std::error::Error for BoundedGridParseError {}
+
+/// Parses one size of the grid, returning it and whether it ends with a twist
+fn parse_size(size: &str) -> Result<(usize, bool), BoundedGridParseError> {
+    let (digits, twisted) = match size.strip_suffix('*') {
+        Some(digits) => (digits, true),
+        None => (size, false),
+    };
+    let value = digits
+        .trim()
+        .parse()
+        .map_err(|_| BoundedGridParseError::InvalidSize(size.to_string()))?;
+    Ok((value, twisted))
+}
+
+impl FromStr for BoundedGrid {
+    type Err = BoundedGridParseError;
+
+    /// Parses "T50,50", "P50,50", "K50*,50", "C50,50" and the single size shorthand "T50".
+    /// Tori with a size of 0 are cylinders.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let kind = s.chars().next().ok_or(BoundedGridParseError::Empty)?;
+        let sizes = &s[kind.len_utf8()..];
+        let ((width, width_twisted), (height, height_twisted)) = match sizes.split_once(',') {
+            Some((width, height)) => (parse_size(width)?, parse_size(height)?),
+            None => {
+                let size = parse_size(sizes)?;
+                (size, size)
+            }
+        };
+        let topology = match (kind.to_ascii_uppercase(), width, height) {
+            ('K', _, _) if width_twisted && height_twisted => {
+                return Err(BoundedGridParseError::MisplacedTwist)
+            }
+            // Golly twists the top and bottom edges unless told otherwise
+            ('K', _, _) => Topology::KleinBottle {
+                twisted_x: !height_twisted,
+            },
+            ('T' | 'P' | 'C', _, _) if width_twisted || height_twisted => {
+                return Err(BoundedGridParseError::MisplacedTwist)
+            }
+            ('T', 0, 0) | ('P', _, _) => Topology::Plane,
+            ('T', _, 0) => Topology::VerticalCylinder,
+            ('T', 0, _) => Topology::HorizontalCylinder,
+            ('T', _, _) => Topology::Torus,
+            ('C', _, _) => Topology::CrossSurface,
+            _ => return Err(BoundedGridParseError::UnknownTopology(kind)),
+        };
+        if matches!(
+            topology,
+            Topology::KleinBottle { .. } | Topology::CrossSurface
+        ) && (width == 0 || height == 0)
+        {
+            return Err(BoundedGridParseError::InvalidSize(sizes.to_string()));
+        }
+        Ok(Self {
+            topology,
+            width,
+            height,
+        })
+    }
+}
+
+impl fmt::Display for BoundedGrid {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (width, height) = (self.width, self.height);
+        match self.topology {
+            Topology::Torus => write!(f, "T{},{}", width, height),
+            Topology::Plane => write!(f, "P{},{}", width, height),
+            Topology::VerticalCylinder => write!(f, "T{},0", width),
+            Topology::HorizontalCylinder => write!(f, "T0,{}", height),
+            Topology::KleinBottle { twisted_x: true } => write!(f, "K{}*,{}", width, height),
+            Topology::KleinBottle { twisted_x: false } => write!(f, "K{},{}*", width, height),
+            Topology::CrossSurface => write!(f, "C{},{}", width, height),
+        }
+    }
+}
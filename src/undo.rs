@@ -0,0 +1,338 @@
+use std::collections::{HashMap, VecDeque};
+use std::mem::size_of;
+
+use crate::rule::Rule;
+use crate::state::{Coord, StateGrid};
+use crate::topology::Topology;
+use crate::Universe;
+
+/// The memory in MiB the undo history may use, unless the config or the command line set it
+pub const DEFAULT_MEMORY_MIB: usize = 64;
+
+/// A change to the cells. Applying it a second time reverts it.
+enum Change {
+    /// Edits like drawing or clearing, stored as the bits that flipped in the state of each
+    /// changed cell
+    Edit {
+        flipped: Vec<(Coord, u8)>,
+        generation_before: u64,
+        generation_after: u64,
+    },
+    /// Steps without edits in between, stored like edits. Every step adds the bits it flipped,
+    /// cells that flip back to their state from the start of the run drop out.
+    Run {
+        flipped: HashMap<Coord, u8>,
+        generation_before: u64,
+        generation_after: u64,
+    },
+    /// A loaded pattern, stored as the cells, rule and topology it replaced. Applying it swaps
+    /// them with the current ones.
+    Load {
+        grid: Box<StateGrid>,
+        rule: Rule,
+        topology: Topology,
+    },
+}
+
+impl Change {
+    fn memory(&self) -> usize {
+        let cells = match self {
+            Self::Edit { flipped, .. } => flipped.len(),
+            Self::Run { flipped, .. } => flipped.len(),
+            Self::Load { grid, .. } => grid.population() as usize,
+        };
+        // Bounded grids keep every cell, dead or alive
+        let board = match self {
+            Self::Load { grid, .. } => match grid.as_ref() {
+                StateGrid::Bounded(grid) => grid.width() * grid.height(),
+                _ => 0,
+            },
+            _ => 0,
+        };
+        size_of::<Self>() + cells * size_of::<(Coord, u8)>() + board
+    }
+
+    fn undo(&mut self, universe: &mut Universe) {
+        match self {
+            Self::Edit {
+                flipped,
+                generation_before,
+                ..
+            } => {
+                flip(flipped.iter().copied(), universe.grid_mut());
+                universe.grid_mut().set_generation(*generation_before);
+            }
+            Self::Run {
+                flipped,
+                generation_before,
+                ..
+            } => {
+                flip(
+                    flipped.iter().map(|(pos, bits)| (*pos, *bits)),
+                    universe.grid_mut(),
+                );
+                universe.grid_mut().set_generation(*generation_before);
+            }
+            Self::Load { .. } => self.swap(universe),
+        }
+    }
+
+    fn redo(&mut self, universe: &mut Universe) {
+        match self {
+            Self::Edit {
+                flipped,
+                generation_after,
+                ..
+            } => {
+                flip(flipped.iter().copied(), universe.grid_mut());
+                universe.grid_mut().set_generation(*generation_after);
+            }
+            Self::Run {
+                flipped,
+                generation_after,
+                ..
+            } => {
+                flip(
+                    flipped.iter().map(|(pos, bits)| (*pos, *bits)),
+                    universe.grid_mut(),
+                );
+                universe.grid_mut().set_generation(*generation_after);
+            }
+            Self::Load { .. } => self.swap(universe),
+        }
+    }
+
+    fn swap(&mut self, universe: &mut Universe) {
+        if let Self::Load {
+            grid,
+            rule,
+            topology,
+        } = self
+        {
+            std::mem::swap(universe.grid_mut(), grid.as_mut());
+            let current_rule = universe.rule().clone();
+            universe.set_rule(std::mem::replace(rule, current_rule));
+            let current_topology = universe.topology();
+            universe.set_topology(std::mem::replace(topology, current_topology));
+        }
+    }
+}
+
+/// Manual edits, simulation runs and loaded patterns that can be undone. The oldest changes are
+/// forgotten once the memory limit is reached.
+pub struct History {
+    undo: VecDeque<Change>,
+    redo: Vec<Change>,
+    memory_limit: usize,
+    memory_used: usize,
+    /// Whether the next toggled cell belongs to the last change, like cells drawn in one stroke
+    extend_last: bool,
+    /// Whether the next step belongs to the last change
+    in_run: bool,
+}
+
+impl Default for History {
+    fn default() -> Self {
+        Self::new(DEFAULT_MEMORY_MIB << 20)
+    }
+}
+
+impl History {
+    /// A history using at most `memory_limit` bytes, 0 keeps nothing to undo
+    pub fn new(memory_limit: usize) -> Self {
+        Self {
+            undo: VecDeque::new(),
+            redo: vec![],
+            memory_limit,
+            memory_used: 0,
+            extend_last: false,
+            in_run: false,
+        }
+    }
+
+    /// The bytes the changes that can be undone take up
+    pub fn memory_used(&self) -> usize {
+        self.memory_used
+    }
+
+    /// The live cells and their states to pass to `record` once the change is done
+    pub fn snapshot(state_grid: &StateGrid) -> HashMap<Coord, u8> {
+        state_grid.cells().into_iter().collect()
+    }
+
+    /// Records the difference between the cells before a change and the current cells
+    pub fn record(
+        &mut self,
+        cells_before: &HashMap<Coord, u8>,
+        generation_before: u64,
+        state_grid: &StateGrid,
+    ) {
+        self.push(Change::Edit {
+            flipped: difference(cells_before, state_grid),
+            generation_before,
+            generation_after: state_grid.generation(),
+        });
+    }
+
+    /// Records a loaded pattern, undoing it brings back the replaced cells, rule and topology
+    pub fn record_load(&mut self, grid: StateGrid, rule: Rule, topology: Topology) {
+        self.push(Change::Load {
+            grid: Box::new(grid),
+            rule,
+            topology,
+        });
+    }
+
+    /// Whether the next step is recorded, which needs the cells before it passed to
+    /// `record_step`. Patterns too large to keep a copy of can't be undone, they clear the
+    /// history.
+    pub fn records_step(&mut self, state_grid: &StateGrid) -> bool {
+        let memory = state_grid.population() as usize * size_of::<(Coord, u8)>();
+        if memory > self.memory_limit {
+            self.clear();
+            return false;
+        }
+        true
+    }
+
+    /// Adds the cells a step flipped to the current run, or starts a new run. Steps after that
+    /// belong to the same run until the cells are edited or `end_run` is called.
+    pub fn record_step(
+        &mut self,
+        cells_before: &[(Coord, u8)],
+        generation_before: u64,
+        state_grid: &StateGrid,
+    ) {
+        if !self.in_run {
+            self.push(Change::Run {
+                flipped: HashMap::new(),
+                generation_before,
+                generation_after: generation_before,
+            });
+            self.in_run = true;
+        }
+        let (flipped, generation_after) = match self.undo.back_mut() {
+            Some(Change::Run {
+                flipped,
+                generation_after,
+                ..
+            }) => (flipped, generation_after),
+            // The run grew past the memory limit and was forgotten
+            _ => return,
+        };
+        let cells_before = cells_before.iter().copied().collect();
+        let memory_before = flipped.len() * size_of::<(Coord, u8)>();
+        for (pos, bits) in difference(&cells_before, state_grid) {
+            let run_bits = flipped.entry(pos).or_insert(0);
+            *run_bits ^= bits;
+            if *run_bits == 0 {
+                flipped.remove(&pos);
+            }
+        }
+        *generation_after = state_grid.generation();
+        self.memory_used += flipped.len() * size_of::<(Coord, u8)>();
+        self.memory_used -= memory_before;
+        self.enforce_limit();
+    }
+
+    /// Records a single cell changing from `state_before` to `state_after`. Cells changed without
+    /// `end_stroke` being called in between are undone together.
+    pub fn record_cell(&mut self, pos: Coord, state_before: u8, state_after: u8, generation: u64) {
+        let flipped = (pos, state_before ^ state_after);
+        if self.extend_last && self.redo.is_empty() {
+            if let Some(Change::Edit { flipped: cells, .. }) = self.undo.back_mut() {
+                cells.push(flipped);
+                self.memory_used += size_of::<(Coord, u8)>();
+                self.enforce_limit();
+                return;
+            }
+        }
+        self.push(Change::Edit {
+            flipped: vec![flipped],
+            generation_before: generation,
+            generation_after: generation,
+        });
+        self.extend_last = true;
+    }
+
+    pub fn end_stroke(&mut self) {
+        self.extend_last = false;
+    }
+
+    /// Makes the next step start a new run, so pausing or a step command can be undone on its own
+    pub fn end_run(&mut self) {
+        self.in_run = false;
+    }
+
+    fn push(&mut self, change: Change) {
+        self.redo.clear();
+        self.extend_last = false;
+        self.in_run = false;
+        self.memory_used += change.memory();
+        self.undo.push_back(change);
+        self.enforce_limit();
+    }
+
+    fn enforce_limit(&mut self) {
+        while self.memory_used > self.memory_limit {
+            match self.undo.pop_front() {
+                Some(change) => self.memory_used -= change.memory(),
+                None => break,
+            }
+        }
+    }
+
+    /// Forgets every change, for when the cells changed in a way that wasn't recorded
+    pub fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+        self.memory_used = 0;
+        self.extend_last = false;
+        self.in_run = false;
+    }
+
+    pub fn undo(&mut self, universe: &mut Universe) -> bool {
+        let mut change = match self.undo.pop_back() {
+            Some(change) => change,
+            None => return false,
+        };
+        self.memory_used -= change.memory();
+        change.undo(universe);
+        self.redo.push(change);
+        self.extend_last = false;
+        self.in_run = false;
+        true
+    }
+
+    pub fn redo(&mut self, universe: &mut Universe) -> bool {
+        let mut change = match self.redo.pop() {
+            Some(change) => change,
+            None => return false,
+        };
+        change.redo(universe);
+        self.memory_used += change.memory();
+        self.undo.push_back(change);
+        self.extend_last = false;
+        self.in_run = false;
+        true
+    }
+}
+
+/// The bits that flipped in the state of each cell that changed since `cells_before`
+fn difference(cells_before: &HashMap<Coord, u8>, state_grid: &StateGrid) -> Vec<(Coord, u8)> {
+    let cells_after = state_grid.cells();
+    let changed = cells_after
+        .iter()
+        .map(|(pos, state)| (*pos, state ^ cells_before.get(pos).copied().unwrap_or(0)));
+    let died = cells_before
+        .iter()
+        .filter(|(pos, _)| state_grid.get(**pos) == 0)
+        .map(|(pos, state)| (*pos, *state));
+    changed.chain(died).filter(|(_, bits)| *bits != 0).collect()
+}
+
+fn flip(flipped: impl Iterator<Item = (Coord, u8)>, state_grid: &mut StateGrid) {
+    for (pos, bits) in flipped {
+        state_grid.set(pos, state_grid.get(pos) ^ bits);
+    }
+}
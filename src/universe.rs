@@ -0,0 +1,145 @@
+use crate::executor::{Executor, Serial};
+use crate::rule::Rule;
+use crate::state::{BoundingBox, Coord, DenseGrid, GridSize, StateGrid};
+use crate::topology::Topology;
+
+/// A whole simulation: the cells, the rule they follow and how the edges of a bounded grid are
+/// joined. Starts out as an empty bounded torus running Conway's Life.
+pub struct Universe {
+    grid: StateGrid,
+    rule: Rule,
+    topology: Topology,
+    executor: Box<dyn Executor>,
+}
+
+impl Universe {
+    pub fn new(size: GridSize) -> Self {
+        Self {
+            grid: StateGrid::Bounded(DenseGrid::new(size)),
+            rule: Rule::default(),
+            topology: Topology::Torus,
+            executor: Box::new(Serial),
+        }
+    }
+
+    pub fn rule(&self) -> &Rule {
+        &self.rule
+    }
+
+    /// The cells keep their states, even ones the new rule doesn't have
+    pub fn set_rule(&mut self, rule: Rule) {
+        self.rule = rule;
+    }
+
+    pub fn topology(&self) -> Topology {
+        self.topology
+    }
+
+    pub fn set_topology(&mut self, topology: Topology) {
+        self.topology = topology;
+    }
+
+    /// Runs the bands of parallel steps on the executor, by default they run one after another
+    pub fn set_executor(&mut self, executor: Box<dyn Executor>) {
+        self.executor = executor;
+    }
+
+    /// The engine and storage of the cells
+    pub fn grid(&self) -> &StateGrid {
+        &self.grid
+    }
+
+    pub fn grid_mut(&mut self) -> &mut StateGrid {
+        &mut self.grid
+    }
+
+    /// The state of the cell, 0 if it is dead
+    pub fn get(&self, pos: Coord) -> u8 {
+        self.grid.get(pos)
+    }
+
+    pub fn set(&mut self, pos: Coord, state: u8) {
+        self.grid.set(pos, state);
+    }
+
+    /// Sets a cell to `state`, or kills it if it already is in that state
+    pub fn toggle(&mut self, pos: Coord, state: u8) {
+        self.grid.toggle(pos, state);
+    }
+
+    /// Fills `area` with a random soup, every cell alive with a chance of one half. The same seed
+    /// gives the same soup.
+    pub fn randomize(&mut self, area: BoundingBox, seed: u64) {
+        let mut random = seed;
+        for x in area.min.x..=area.max.x {
+            for y in area.min.y..=area.max.y {
+                // SplitMix64, which turns even small or zero seeds into well mixed bits
+                random = random.wrapping_add(0x9e37_79b9_7f4a_7c15);
+                let mut bits = random;
+                bits = (bits ^ bits >> 30).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+                bits = (bits ^ bits >> 27).wrapping_mul(0x94d0_49bb_1331_11eb);
+                bits ^= bits >> 31;
+                self.grid.set(Coord::new(x, y), (bits >> 63) as u8);
+            }
+        }
+    }
+
+    /// Advances the simulation by one generation
+    pub fn step(&mut self) {
+        self.step_pow2(0);
+    }
+
+    /// Advances the simulation by 2^`step_log2` generations, see `StateGrid::step_pow2`
+    pub fn step_pow2(&mut self, step_log2: u32) {
+        self.grid
+            .step_pow2(&self.rule, self.topology, step_log2, self.executor.as_ref());
+    }
+
+    /// Advances the simulation by any number of generations, in steps of powers of two so
+    /// HashLife can skip ahead
+    pub fn advance(&mut self, generations: u64) {
+        for step_log2 in (0..u64::BITS).rev() {
+            if generations >> step_log2 & 1 == 1 {
+                self.step_pow2(step_log2);
+            }
+        }
+    }
+
+    pub fn generation(&self) -> u64 {
+        self.grid.generation()
+    }
+
+    /// The number of live and dying cells
+    pub fn population(&self) -> u64 {
+        self.grid.population()
+    }
+
+    /// The live and dying cells with their states
+    pub fn cells(&self) -> impl Iterator<Item = (Coord, u8)> {
+        self.grid.cells().into_iter()
+    }
+
+    /// The live and dying cells inside of `area` with their states
+    pub fn cells_in(&self, area: BoundingBox) -> impl Iterator<Item = (Coord, u8)> {
+        self.grid.cells_in(area).into_iter()
+    }
+
+    pub fn bounding_box(&self) -> Option<BoundingBox> {
+        self.grid.bounding_box()
+    }
+
+    /// Kills every cell and resets the generation
+    pub fn clear(&mut self) {
+        self.grid.clear();
+    }
+
+    /// Resizes a bounded grid, unbounded grids are left as they are
+    pub fn resize(&mut self, size: GridSize) {
+        self.grid.resize(size);
+    }
+
+    /// Switches to the next engine, see `StateGrid::next_engine`
+    pub fn next_engine(&mut self, size: GridSize) {
+        self.grid.next_engine(size, &self.rule);
+    }
+}
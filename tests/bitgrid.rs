@@ -0,0 +1,91 @@
+//! Stepping bit-packed rows must give the same cells as the byte grid, which counts the neighbors
+//! of every cell one by one
+
+use std::str::FromStr;
+
+use game_of_life::bitgrid::BitGrid;
+use game_of_life::rule::Rule;
+use game_of_life::state::step_life_like;
+use game_of_life::topology::Topology;
+
+const TOPOLOGIES: [Topology; 7] = [
+    Topology::Torus,
+    Topology::Plane,
+    Topology::VerticalCylinder,
+    Topology::HorizontalCylinder,
+    Topology::KleinBottle { twisted_x: true },
+    Topology::KleinBottle { twisted_x: false },
+    Topology::CrossSurface,
+];
+
+/// A xorshift generator, so failures can be reproduced
+struct Random(u64);
+
+impl Random {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn below(&mut self, n: u64) -> u64 {
+        self.next() % n
+    }
+}
+
+#[test]
+fn step_matches_byte_grid() {
+    let mut random = Random(0x2545_f491_4f6c_dd1d);
+    for rulestring in [
+        "B3/S23",
+        "B36/S23",
+        "B2/S",
+        "B1357/S1357",
+        "B0/S8",
+        "B0123478/S34678",
+        "B01/S",
+        "B2/S34H",
+        "B0/S2H",
+        "B13/S012V",
+        "B0/S4V",
+    ] {
+        let rule = match Rule::from_str(rulestring) {
+            Ok(Rule::LifeLike(rule)) => rule,
+            _ => unreachable!(),
+        };
+        for topology in TOPOLOGIES {
+            for _ in 0..3 {
+                // Up to three 64 bit words per row
+                let width = 1 + random.below(192) as usize;
+                // Twisted edges need a square grid
+                let height = match topology {
+                    Topology::KleinBottle { .. } | Topology::CrossSurface => width,
+                    _ => 1 + random.below(48) as usize,
+                };
+                let mut grid = BitGrid::new(width, height);
+                let mut cells = vec![vec![0; height]; width];
+                let density = 1 + random.below(4);
+                for (x, column) in cells.iter_mut().enumerate() {
+                    for (y, cell) in column.iter_mut().enumerate() {
+                        if random.below(density + 1) == 0 {
+                            grid.set(x, y, true);
+                            *cell = 1;
+                        }
+                    }
+                }
+                for generation in 1..=8 {
+                    grid.step(&rule, topology);
+                    step_life_like(&mut cells, &rule, topology);
+                    let differs = (0..width)
+                        .flat_map(|x| (0..height).map(move |y| (x, y)))
+                        .find(|&(x, y)| grid.get(x, y) != (cells[x][y] == 1));
+                    assert_eq!(
+                        differs, None,
+                        "{rulestring} on a {width}x{height} {topology:?} after {generation} steps"
+                    );
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,274 @@
+use game_of_life::isotropic::Isotropic;
+use game_of_life::rule::{Rule, RuleParseError};
+use game_of_life::state::{Coord, GridSize};
+use game_of_life::topology::Topology;
+use game_of_life::Universe;
+
+const GLIDER: [(i64, i64); 5] = [(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)];
+
+/// Hensel's picture of every letter for 1 to 4 live neighbors, drawn around the cell in the
+/// middle. 5 to 7 neighbors use the same pictures with live and dead neighbors swapped.
+const LETTERS: [(usize, char, [&str; 3]); 31] = [
+    (1, 'c', ["..o", "...", "..."]),
+    (1, 'e', [".o.", "...", "..."]),
+    (2, 'c', ["..o", "...", "..o"]),
+    (2, 'e', [".o.", "..o", "..."]),
+    (2, 'k', [".o.", "...", "..o"]),
+    (2, 'a', [".oo", "...", "..."]),
+    (2, 'i', [".o.", "...", ".o."]),
+    (2, 'n', ["..o", "...", "o.."]),
+    (3, 'c', ["..o", "...", "o.o"]),
+    (3, 'e', [".o.", "..o", ".o."]),
+    (3, 'k', [".o.", "..o", "o.."]),
+    (3, 'a', [".oo", "..o", "..."]),
+    (3, 'i', ["ooo", "...", "..."]),
+    (3, 'n', [".oo", "...", "..o"]),
+    (3, 'y', [".o.", "...", "o.o"]),
+    (3, 'q', [".oo", "...", "o.."]),
+    (3, 'j', [".oo", "o..", "..."]),
+    (3, 'r', [".oo", "...", ".o."]),
+    (4, 'c', ["o.o", "...", "o.o"]),
+    (4, 'e', [".o.", "o.o", ".o."]),
+    (4, 'k', [".oo", "o..", "..o"]),
+    (4, 'a', [".oo", "..o", "..o"]),
+    (4, 'i', [".oo", "...", ".oo"]),
+    (4, 'n', ["ooo", "...", "..o"]),
+    (4, 'y', [".oo", "...", "o.o"]),
+    (4, 'q', [".oo", "..o", "o.."]),
+    (4, 'j', [".oo", "o..", ".o."]),
+    (4, 'r', [".oo", "..o", ".o."]),
+    (4, 't', ["ooo", "...", ".o."]),
+    (4, 'w', [".oo", "o..", "o.."]),
+    (4, 'z', [".oo", "...", "oo."]),
+];
+
+/// A pattern stepped 8 generations under `rule`, with the population after every generation and
+/// the final cells, whose top left corner moved by `offset`
+struct Soup {
+    rule: &'static str,
+    start: &'static [&'static str],
+    populations: [u64; 8],
+    result: &'static [&'static str],
+    offset: (i64, i64),
+}
+
+/// The rotations and reflections of the plane as matrices
+const ORIENTATIONS: [[i64; 4]; 8] = [
+    [1, 0, 0, 1],
+    [0, -1, 1, 0],
+    [-1, 0, 0, -1],
+    [0, 1, -1, 0],
+    [-1, 0, 0, 1],
+    [0, 1, 1, 0],
+    [1, 0, 0, -1],
+    [0, -1, -1, 0],
+];
+
+fn parse(s: &str) -> Result<Isotropic, RuleParseError> {
+    s.parse()
+}
+
+fn universe(rule: Isotropic) -> Universe {
+    let mut universe = Universe::new(GridSize {
+        width: 32,
+        height: 32,
+    });
+    universe.set_topology(Topology::Plane);
+    universe.set_rule(Rule::Isotropic(rule));
+    universe
+}
+
+/// The live cells of a picture, with its top left corner at `(x, y)`
+fn pattern(rows: &[&str], x: i64, y: i64) -> Vec<Coord> {
+    let mut cells: Vec<Coord> = rows
+        .iter()
+        .zip(y..)
+        .flat_map(|(row, y)| {
+            row.chars()
+                .zip(x..)
+                .filter(|(c, _)| *c == 'o')
+                .map(move |(_, x)| Coord::new(x, y))
+        })
+        .collect();
+    cells.sort_unstable();
+    cells
+}
+
+fn live_cells(universe: &Universe) -> Vec<Coord> {
+    let mut cells: Vec<Coord> = universe.cells().map(|(pos, _)| pos).collect();
+    cells.sort_unstable();
+    cells
+}
+
+#[test]
+fn rulestrings_round_trip() {
+    for rulestring in ["B2-a/S12", "B2ce3-jk/S23", "B2ce3-jk/S23/C4"] {
+        assert_eq!(parse(rulestring).unwrap().to_string(), rulestring);
+        let rule: Rule = rulestring.parse().unwrap();
+        assert!(matches!(rule, Rule::Isotropic(_)));
+        assert_eq!(rule.to_string(), rulestring);
+    }
+}
+
+#[test]
+fn totalistic_rules_match_their_counts() {
+    let (birth, survival) = parse("B3/S23").unwrap().totalistic_counts().unwrap();
+    let counts = |included: &[usize]| {
+        let mut counts = [false; 9];
+        for count in included {
+            counts[*count] = true;
+        }
+        counts
+    };
+    assert_eq!(birth, counts(&[3]));
+    assert_eq!(survival, counts(&[2, 3]));
+    // Listing every letter of a count is the same as the bare count
+    assert_eq!(
+        parse("B3aceijknqry/S2aceikn3").unwrap(),
+        parse("B3/S23").unwrap()
+    );
+    assert_eq!(parse("B2-a/S12").unwrap().totalistic_counts(), None);
+}
+
+#[test]
+fn invalid_letters_are_rejected() {
+    assert_eq!(
+        parse("B2z/S23"),
+        Err(RuleParseError::InvalidNeighborhoodLetter("2z".to_string()))
+    );
+    assert_eq!(
+        "B2z/S23".parse::<Rule>(),
+        Err(RuleParseError::InvalidNeighborhoodLetter("2z".to_string()))
+    );
+    assert_eq!(
+        parse("B1-/S23"),
+        Err(RuleParseError::UnexpectedCharacter('-'))
+    );
+}
+
+#[test]
+fn glider_flies_in_every_orientation() {
+    // The glider never has a birth in the 3k arrangement, so it still flies without it
+    let rule: Rule = "B3-k/S23".parse().unwrap();
+    assert!(matches!(rule, Rule::Isotropic(_)));
+    for (flip_x, flip_y) in [(1, 1), (-1, 1), (1, -1), (-1, -1)] {
+        let mut universe = Universe::new(GridSize {
+            width: 32,
+            height: 32,
+        });
+        universe.set_topology(Topology::Plane);
+        universe.set_rule(rule.clone());
+        let start: Vec<Coord> = GLIDER
+            .iter()
+            .map(|(x, y)| Coord::new(16 + flip_x * x, 16 + flip_y * y))
+            .collect();
+        for pos in &start {
+            universe.set(*pos, 1);
+        }
+        for _ in 0..4 {
+            universe.step();
+        }
+        let mut cells: Vec<Coord> = universe.cells().map(|(pos, _)| pos).collect();
+        let mut moved: Vec<Coord> = start
+            .iter()
+            .map(|pos| Coord::new(pos.x + flip_x, pos.y + flip_y))
+            .collect();
+        cells.sort_unstable();
+        moved.sort_unstable();
+        assert_eq!(cells, moved);
+    }
+}
+
+#[test]
+fn every_letter_matches_its_picture_in_every_orientation() {
+    let middle = Coord::new(16, 16);
+    for (count, letter, picture) in LETTERS {
+        let neighbors = pattern(&picture, -1, -1);
+        // Above 4 the complement of a picture is the same letter, 4 has no complements
+        let complements = if count < 4 {
+            vec![false, true]
+        } else {
+            vec![false]
+        };
+        for complement in complements {
+            let (count, neighbors) = match complement {
+                false => (count, neighbors.clone()),
+                true => (
+                    8 - count,
+                    pattern(&["ooo", "o.o", "ooo"], -1, -1)
+                        .into_iter()
+                        .filter(|pos| !neighbors.contains(pos))
+                        .collect(),
+                ),
+            };
+            for [a, b, c, d] in ORIENTATIONS {
+                for (rulestring, born) in [
+                    (format!("B{count}{letter}/S"), true),
+                    (format!("B{count}-{letter}/S"), false),
+                ] {
+                    let mut universe = universe(parse(&rulestring).unwrap());
+                    for pos in &neighbors {
+                        let (x, y) = (a * pos.x + b * pos.y, c * pos.x + d * pos.y);
+                        universe.set(Coord::new(middle.x + x, middle.y + y), 1);
+                    }
+                    universe.step();
+                    assert_eq!(
+                        universe.get(middle) == 1,
+                        born,
+                        "{rulestring} with {neighbors:?} turned by {:?}",
+                        [a, b, c, d]
+                    );
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn soups_match_reference_generations() {
+    // Worked out from the pictures above. The totalistic rules with the same counts end up with
+    // different cells.
+    let soups = [
+        Soup {
+            rule: "B3/S2-i34q",
+            start: &[".o...", ".oooo", "....o", "ooooo"],
+            populations: [13, 16, 17, 18, 17, 22, 21, 31],
+            result: &[
+                "oo......", "o.ooooo.", ".o.oo..o", "o.o.o.oo", "oo..o.oo", "oooooo..", "...ooo..",
+            ],
+            offset: (0, 0),
+        },
+        Soup {
+            rule: "B2-a/S12",
+            start: &[".oo..", "oo.o.", "..o.o", "oo...", "..o.."],
+            populations: [7, 13, 10, 16, 13, 19, 11, 23],
+            result: &[
+                "....ooo.", "....o.o.", "o...o..o", "o.o.ooo.", "ooo.o...", "oo.oo...", "..o.....",
+                "..o.....",
+            ],
+            offset: (-1, -1),
+        },
+    ];
+    for Soup {
+        rule: rulestring,
+        start,
+        populations,
+        result,
+        offset: (dx, dy),
+    } in soups
+    {
+        let mut universe = universe(parse(rulestring).unwrap());
+        for pos in pattern(start, 12, 12) {
+            universe.set(pos, 1);
+        }
+        for population in populations {
+            universe.step();
+            assert_eq!(universe.population(), population, "{rulestring}");
+        }
+        assert_eq!(
+            live_cells(&universe),
+            pattern(result, 12 + dx, 12 + dy),
+            "{rulestring}"
+        );
+    }
+}
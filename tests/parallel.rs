@@ -0,0 +1,89 @@
+//! Stepping bands of rows in parallel must give the same cells as the byte grid, which counts
+//! the neighbors of every cell one by one
+
+use std::str::FromStr;
+
+use game_of_life::bitgrid::BitGrid;
+use game_of_life::executor::Threads;
+use game_of_life::rule::Rule;
+use game_of_life::state::step_life_like;
+use game_of_life::topology::Topology;
+
+const TOPOLOGIES: [Topology; 7] = [
+    Topology::Torus,
+    Topology::Plane,
+    Topology::VerticalCylinder,
+    Topology::HorizontalCylinder,
+    Topology::KleinBottle { twisted_x: true },
+    Topology::KleinBottle { twisted_x: false },
+    Topology::CrossSurface,
+];
+
+/// A xorshift generator, so failures can be reproduced
+struct Random(u64);
+
+impl Random {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn below(&mut self, n: u64) -> u64 {
+        self.next() % n
+    }
+}
+
+#[test]
+fn parallel_step_matches_byte_grid() {
+    let mut random = Random(0x9e37_79b9_7f4a_7c15);
+    for rulestring in [
+        "B3/S23",
+        "B36/S23",
+        "B0/S8",
+        "B1357/S1357",
+        "B2/S34H",
+        "B13/S012V",
+    ] {
+        let rule = match Rule::from_str(rulestring) {
+            Ok(Rule::LifeLike(rule)) => rule,
+            _ => unreachable!(),
+        };
+        for topology in TOPOLOGIES {
+            // From a single band up to bands of the minimum height
+            for thread_count in [1, 2, 3, 5, 8] {
+                // Tall enough for every thread to get a band most of the time
+                let height = 1 + random.below(24 * thread_count as u64) as usize;
+                // Twisted edges need a square grid
+                let width = match topology {
+                    Topology::KleinBottle { .. } | Topology::CrossSurface => height,
+                    _ => 1 + random.below(150) as usize,
+                };
+                let mut grid = BitGrid::new(width, height);
+                let mut cells = vec![vec![0; height]; width];
+                let density = 1 + random.below(4);
+                for (x, column) in cells.iter_mut().enumerate() {
+                    for (y, cell) in column.iter_mut().enumerate() {
+                        if random.below(density + 1) == 0 {
+                            grid.set(x, y, true);
+                            *cell = 1;
+                        }
+                    }
+                }
+                for generation in 1..=8 {
+                    grid.step_parallel(&rule, topology, &Threads(thread_count));
+                    step_life_like(&mut cells, &rule, topology);
+                    let differs = (0..width)
+                        .flat_map(|x| (0..height).map(move |y| (x, y)))
+                        .find(|&(x, y)| grid.get(x, y) != (cells[x][y] == 1));
+                    assert_eq!(
+                        differs, None,
+                        "{rulestring} on a {width}x{height} {topology:?} on {thread_count} threads \
+                        after {generation} steps"
+                    );
+                }
+            }
+        }
+    }
+}
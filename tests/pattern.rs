@@ -0,0 +1,272 @@
+use game_of_life::pattern::{life, macrocell, plaintext, rle, Format, Pattern, PatternError};
+use game_of_life::state::Coord;
+
+#[test]
+fn rle_reads_runs_and_multi_state_letters() {
+    let pattern = rle::read("x = 3, y = 2, rule = B3/S23\n2o$2bo!\n").unwrap();
+    assert_eq!(pattern.rule.as_deref(), Some("B3/S23"));
+    assert_eq!(
+        pattern.cells,
+        [
+            (Coord::new(0, 0), 1),
+            (Coord::new(1, 0), 1),
+            (Coord::new(2, 1), 1),
+        ]
+    );
+
+    let pattern = rle::read("x = 5, y = 1\n.A2BpA!\n").unwrap();
+    assert_eq!(
+        pattern.cells,
+        [
+            (Coord::new(1, 0), 1),
+            (Coord::new(2, 0), 2),
+            (Coord::new(3, 0), 2),
+            (Coord::new(4, 0), 25),
+        ]
+    );
+}
+
+#[test]
+fn rle_reads_the_highest_state() {
+    let pattern = rle::read("x = 1, y = 1\nyO!\n").unwrap();
+    assert_eq!(pattern.cells, [(Coord::new(0, 0), 255)]);
+}
+
+#[test]
+fn rle_rejects_states_above_255() {
+    assert_eq!(
+        rle::read("x = 1, y = 1\nyP!\n"),
+        Err(PatternError::InvalidState {
+            line: 2,
+            state: 256
+        })
+    );
+    assert_eq!(
+        rle::read("x = 1, y = 1\nyX!\n"),
+        Err(PatternError::InvalidState {
+            line: 2,
+            state: 264
+        })
+    );
+}
+
+#[test]
+fn rle_rejects_runs_past_the_header_size() {
+    assert_eq!(
+        rle::read("x = 3, y = 1\n999999999o!\n"),
+        Err(PatternError::RunTooLong { line: 2 })
+    );
+    assert_eq!(
+        rle::read("x = 3, y = 1\nb3o!\n"),
+        Err(PatternError::RunTooLong { line: 2 })
+    );
+    assert_eq!(
+        rle::read("x = 3, y = 2\no$o$o!\n"),
+        Err(PatternError::RunTooLong { line: 2 })
+    );
+    // Dead cells take no memory and may reach past the width and height
+    assert!(rle::read("x = 3, y = 1\no9b!\n").is_ok());
+    assert!(rle::read("x = 3, y = 1\no5$!\n").is_ok());
+}
+
+#[test]
+fn rle_rejects_state_prefixes_in_two_state_rules() {
+    assert_eq!(
+        rle::read("x = 1, y = 1, rule = B3/S23\npA!\n"),
+        Err(PatternError::UnexpectedCharacter {
+            line: 2,
+            character: 'p'
+        })
+    );
+    let pattern = rle::read("x = 1, y = 1, rule = B3/S23/C30\npA!\n").unwrap();
+    assert_eq!(pattern.cells, [(Coord::new(0, 0), 25)]);
+}
+
+#[test]
+fn rle_rejects_runs_that_overflow() {
+    assert_eq!(
+        rle::read("x = 3, y = 1\n99999999999999999999o!\n"),
+        Err(PatternError::RunTooLong { line: 2 })
+    );
+    assert_eq!(
+        rle::read("x = 3, y = 1\n9223372036854775807b9223372036854775807b!\n"),
+        Err(PatternError::RunTooLong { line: 2 })
+    );
+    assert_eq!(
+        rle::read("x = 3, y = 1\n9223372036854775807$9223372036854775807$o!\n"),
+        Err(PatternError::RunTooLong { line: 2 })
+    );
+}
+
+/// A glider with a lone cell away from it, so the pattern has an empty row and column
+const CELLS: [(i64, i64); 6] = [(1, 0), (2, 1), (0, 2), (1, 2), (2, 2), (5, 4)];
+
+fn pattern() -> Pattern {
+    let mut pattern = Pattern {
+        cells: CELLS.iter().map(|&(x, y)| (Coord::new(x, y), 1)).collect(),
+        name: Some("Glider".to_string()),
+        ..Pattern::default()
+    };
+    pattern.normalize();
+    pattern
+}
+
+fn sorted(mut cells: Vec<(Coord, u8)>) -> Vec<(Coord, u8)> {
+    cells.sort_unstable();
+    cells
+}
+
+fn round_trip(format: Format, pattern: &Pattern) -> Pattern {
+    format.read(&format.write(pattern).unwrap()).unwrap()
+}
+
+#[test]
+fn plaintext_round_trips() {
+    let mut pattern = pattern();
+    pattern.author = Some("Richard K. Guy".to_string());
+    pattern.comments = vec!["The smallest spaceship".to_string()];
+    assert_eq!(round_trip(Format::Plaintext, &pattern), pattern);
+}
+
+#[test]
+fn life_105_round_trips() {
+    let mut pattern = pattern();
+    pattern.rule = Some("B36/S23".to_string());
+    let read = round_trip(Format::Life105, &pattern);
+    assert_eq!(read.cells, sorted(pattern.cells.clone()));
+    assert_eq!((read.width, read.height), (pattern.width, pattern.height));
+    assert_eq!(read.rule.as_deref(), Some("23/36"));
+}
+
+#[test]
+fn life_106_round_trips() {
+    let pattern = pattern();
+    let read = round_trip(Format::Life106, &pattern);
+    assert_eq!(read.cells, sorted(pattern.cells.clone()));
+    assert_eq!((read.width, read.height), (pattern.width, pattern.height));
+}
+
+#[test]
+fn rle_round_trips_multi_state_cells() {
+    let mut pattern = pattern();
+    pattern.cells[0].1 = 2;
+    pattern.cells[5].1 = 200;
+    pattern.rule = Some("B2/S/C255".to_string());
+    assert_eq!(round_trip(Format::Rle, &pattern), pattern);
+}
+
+#[test]
+fn two_state_formats_reject_dying_cells() {
+    let mut pattern = pattern();
+    pattern.cells[3].1 = 2;
+    for format in [Format::Plaintext, Format::Life105, Format::Life106] {
+        assert_eq!(
+            format.write(&pattern),
+            Err(PatternError::UnsupportedState(2))
+        );
+    }
+}
+
+#[test]
+fn plaintext_rejects_unexpected_characters() {
+    assert_eq!(
+        plaintext::read("!Name: Blinker\nOOO\n.x.\n"),
+        Err(PatternError::UnexpectedCharacter {
+            line: 3,
+            character: 'x'
+        })
+    );
+}
+
+#[test]
+fn life_rejects_missing_headers() {
+    assert_eq!(
+        life::read("0 0\n1 1\n"),
+        Err(PatternError::MissingHeader("#Life 1.06"))
+    );
+}
+
+#[test]
+fn life_rejects_invalid_coordinates() {
+    assert_eq!(
+        life::read("#Life 1.06\n0 0\n1\n"),
+        Err(PatternError::InvalidCoordinates { line: 3 })
+    );
+    assert_eq!(
+        life::read("#Life 1.06\n0 0 0\n"),
+        Err(PatternError::InvalidCoordinates { line: 2 })
+    );
+    assert_eq!(
+        life::read("#Life 1.05\n#P x 0\n*\n"),
+        Err(PatternError::InvalidCoordinates { line: 2 })
+    );
+}
+
+#[test]
+fn life_rejects_coordinates_far_from_the_origin() {
+    assert_eq!(
+        life::read("#Life 1.06\n0 2000000000000000000\n"),
+        Err(PatternError::CoordinateOutOfRange { line: 2 })
+    );
+    assert_eq!(
+        life::read("#Life 1.05\n#P -2000000000000000000 0\n*\n"),
+        Err(PatternError::CoordinateOutOfRange { line: 2 })
+    );
+}
+
+#[test]
+fn life_105_rejects_cells_before_a_block_position() {
+    assert_eq!(
+        life::read("#Life 1.05\n#D Blinker\n***\n"),
+        Err(PatternError::MissingBlockPosition { line: 3 })
+    );
+}
+
+#[test]
+fn life_105_rejects_unexpected_characters() {
+    assert_eq!(
+        life::read("#Life 1.05\n#P 0 0\n*O*\n"),
+        Err(PatternError::UnexpectedCharacter {
+            line: 3,
+            character: 'O'
+        })
+    );
+}
+
+#[test]
+fn macrocell_roots_below_the_leaf_level_stay_centered() {
+    // A root of level n covers -2^(n-1) to 2^(n-1) - 1, with north towards positive y
+    for (text, cells) in [
+        (
+            "1 1 1 0 1\n",
+            vec![Coord::new(-1, 0), Coord::new(0, 0), Coord::new(0, -1)],
+        ),
+        ("1 1 0 0 0\n2 0 0 0 1\n", vec![Coord::new(0, -1)]),
+    ] {
+        let input = format!("[M2] (golly 4.2)\n#R B3/S23\n{text}");
+        let universe = macrocell::read(&input).unwrap().universe;
+        let saved = macrocell::write(&universe, "B3/S23");
+        let reloaded = macrocell::read(&saved).unwrap().universe;
+        for universe in [universe, reloaded] {
+            assert_eq!(universe.population(), cells.len() as u64, "{text}");
+            for pos in &cells {
+                assert!(universe.get(*pos), "{text} {pos:?}");
+            }
+        }
+    }
+}
+
+#[test]
+fn plaintext_keeps_blank_rows_and_columns_before_the_cells() {
+    let input = "!Name: Offset\n\n..O\n...O\n";
+    let pattern = plaintext::read(input).unwrap();
+    assert_eq!(
+        pattern.cells,
+        [(Coord::new(2, 1), 1), (Coord::new(3, 2), 1)]
+    );
+    assert_eq!((pattern.width, pattern.height), (4, 3));
+    assert_eq!(
+        plaintext::write(&pattern).unwrap(),
+        "!Name: Offset\n....\n..O.\n...O\n"
+    );
+}
@@ -0,0 +1,110 @@
+use game_of_life::rule::{LifeLike, Neighbors, Rule, RuleParseError};
+
+/// A two state Moore rule with the given birth and survival counts
+fn life_like(birth: &[usize], survival: &[usize]) -> LifeLike {
+    let mut rule = LifeLike {
+        birth: [false; 9],
+        survival: [false; 9],
+        states: 2,
+        neighbors: Neighbors::Moore,
+    };
+    for n in birth {
+        rule.birth[*n] = true;
+    }
+    for n in survival {
+        rule.survival[*n] = true;
+    }
+    rule
+}
+
+fn parse(s: &str) -> Result<LifeLike, RuleParseError> {
+    s.parse()
+}
+
+#[test]
+fn bs_and_sb_notations_parse() {
+    assert_eq!(parse("B3/S23"), Ok(LifeLike::default()));
+    assert_eq!(parse("b36/s23"), Ok(life_like(&[3, 6], &[2, 3])));
+    assert_eq!(parse("S23/B3"), Ok(LifeLike::default()));
+    assert_eq!(parse("B3S23"), Ok(LifeLike::default()));
+    assert_eq!(parse("23/3"), Ok(LifeLike::default()));
+    assert_eq!(parse("B/S"), Ok(life_like(&[], &[])));
+    assert_eq!(parse("B2/S/C3"), parse("/2/3"));
+    assert_eq!(parse("B2/S/C3").map(|rule| rule.states), Ok(3));
+}
+
+#[test]
+fn rulestrings_round_trip() {
+    for rulestring in [
+        "B3/S23",
+        "B36/S23",
+        "B/S012345678",
+        "B2/S/C3",
+        "B2/S34H",
+        "B2/S013V",
+    ] {
+        let rule: Rule = rulestring.parse().unwrap();
+        assert_eq!(rule.to_string(), rulestring);
+    }
+}
+
+#[test]
+fn empty_rulestrings_are_rejected() {
+    assert_eq!(parse(""), Err(RuleParseError::Empty));
+    assert_eq!(parse("  "), Err(RuleParseError::Empty));
+}
+
+#[test]
+fn duplicate_sections_are_rejected() {
+    assert_eq!(
+        parse("B3/S23/B6"),
+        Err(RuleParseError::DuplicateSection('B'))
+    );
+    assert_eq!(
+        parse("B3/S2/S3"),
+        Err(RuleParseError::DuplicateSection('S'))
+    );
+    assert_eq!(
+        parse("B2/S/C3/C4"),
+        Err(RuleParseError::DuplicateSection('C'))
+    );
+}
+
+#[test]
+fn counts_above_8_are_rejected() {
+    assert_eq!(
+        parse("B39/S23"),
+        Err(RuleParseError::InvalidNeighborCount('9'))
+    );
+    assert_eq!(
+        parse("239/3"),
+        Err(RuleParseError::InvalidNeighborCount('9'))
+    );
+}
+
+#[test]
+fn missing_sections_are_rejected() {
+    assert_eq!(parse("B3"), Err(RuleParseError::MissingSection('S')));
+    assert_eq!(parse("S23"), Err(RuleParseError::MissingSection('B')));
+    assert_eq!(parse("23"), Err(RuleParseError::MissingSeparator));
+}
+
+#[test]
+fn other_malformed_rulestrings_are_rejected() {
+    assert_eq!(
+        parse("3B/S23"),
+        Err(RuleParseError::DigitWithoutSection('3'))
+    );
+    assert_eq!(
+        parse("B3/S23x"),
+        Err(RuleParseError::UnexpectedCharacter('x'))
+    );
+    assert_eq!(
+        parse("B2/S/C1"),
+        Err(RuleParseError::InvalidStateCount("1".to_string()))
+    );
+    assert_eq!(
+        parse("B5/S2V"),
+        Err(RuleParseError::CountOutsideNeighborhood(5))
+    );
+}
@@ -0,0 +1,163 @@
+use game_of_life::ruletable::{RuleTable, RuleTableError, TableNeighborhood};
+
+/// WireWorld with 1 for electron heads, 2 for electron tails and 3 for wire
+const WIREWORLD: &str = "\
+@RULE WireWorld
+
+@TABLE
+n_states:4
+neighborhood:Moore
+symmetries:permute
+var a={0,1,2,3}
+var b={a}
+var c={a}
+var d={a}
+var e={a}
+var f={a}
+var g={a}
+var h={a}
+var o={0,2,3}
+var p={o}
+var q={o}
+var r={o}
+var s={o}
+var t={o}
+var u={o}
+# Heads become tails and tails become wire
+1,a,b,c,d,e,f,g,h,2
+2,a,b,c,d,e,f,g,h,3
+# Wire next to one or two heads becomes a head
+3,1,o,p,q,r,s,t,u,1
+3,1,1,o,p,q,r,s,t,1
+
+@COLORS
+1 0 128 255
+2 255 255 255
+3 255 128 0
+";
+
+fn table(s: &str) -> RuleTable {
+    s.parse().unwrap()
+}
+
+#[test]
+fn wireworld_transitions() {
+    let wireworld = table(WIREWORLD);
+    assert_eq!(wireworld.name, "WireWorld");
+    assert_eq!(wireworld.states, 4);
+    assert_eq!(wireworld.neighborhood, TableNeighborhood::Moore);
+    assert_eq!(wireworld.colors[1], Some([0, 128, 255]));
+    assert_eq!(wireworld.next_cell(&[1, 3, 3, 0, 0, 0, 0, 0, 0]), 2);
+    assert_eq!(wireworld.next_cell(&[2, 1, 1, 1, 3, 2, 0, 0, 0]), 3);
+    // The heads may be any of the neighbors
+    assert_eq!(wireworld.next_cell(&[3, 1, 0, 0, 0, 0, 0, 0, 0]), 1);
+    assert_eq!(wireworld.next_cell(&[3, 0, 0, 3, 0, 0, 2, 0, 1]), 1);
+    assert_eq!(wireworld.next_cell(&[3, 0, 1, 3, 0, 0, 0, 1, 0]), 1);
+    // Three heads or none leave the wire as it is
+    assert_eq!(wireworld.next_cell(&[3, 1, 0, 1, 0, 3, 0, 1, 0]), 3);
+    assert_eq!(wireworld.next_cell(&[3, 2, 3, 3, 0, 0, 0, 0, 0]), 3);
+    // No transition starts from an empty cell
+    assert_eq!(wireworld.next_cell(&[0, 1, 1, 1, 0, 0, 0, 0, 0]), 0);
+}
+
+#[test]
+fn bound_variables_take_the_same_state_in_every_rotation_and_reflection() {
+    let table = table(
+        "@RULE Pairs
+@TABLE
+n_states:3
+neighborhood:Moore
+symmetries:rotate4reflect
+var a={1,2}
+var b={0,1,2}
+# An empty cell with the same state north and north east of it takes that state
+0,a,a,0,0,0,0,0,b,a
+",
+    );
+    assert_eq!(table.next_cell(&[0, 1, 1, 0, 0, 0, 0, 0, 0]), 1);
+    assert_eq!(table.next_cell(&[0, 2, 2, 0, 0, 0, 0, 0, 2]), 2);
+    assert_eq!(table.next_cell(&[0, 1, 2, 0, 0, 0, 0, 0, 0]), 0);
+    // Rotated a quarter turn clockwise, to the east and south east with any state north east
+    assert_eq!(table.next_cell(&[0, 0, 1, 2, 2, 0, 0, 0, 0]), 2);
+    assert_eq!(table.next_cell(&[0, 1, 0, 2, 2, 0, 0, 0, 0]), 0);
+    // Reflected, to the north and north west
+    assert_eq!(table.next_cell(&[0, 1, 0, 0, 0, 0, 0, 0, 1]), 1);
+    // An eighth turn isn't one of the symmetries, the reflection onto the north east and east
+    // needs an empty cell north
+    assert_eq!(table.next_cell(&[0, 0, 1, 1, 2, 0, 0, 0, 0]), 1);
+    assert_eq!(table.next_cell(&[0, 2, 1, 1, 0, 0, 0, 0, 0]), 0);
+}
+
+#[test]
+fn permute_matches_any_order_of_variables() {
+    let table = table(
+        "@RULE Sum
+@TABLE
+n_states:3
+neighborhood:vonNeumann
+symmetries:permute
+var a={1,2}
+var b={0,2}
+0,a,b,0,0,1
+",
+    );
+    assert_eq!(table.next_cell(&[0, 1, 0, 0, 0]), 1);
+    assert_eq!(table.next_cell(&[0, 0, 0, 2, 2]), 1);
+    assert_eq!(table.next_cell(&[0, 0, 0, 0, 2]), 1);
+    assert_eq!(table.next_cell(&[0, 1, 0, 1, 0]), 0);
+    assert_eq!(table.next_cell(&[0, 2, 2, 2, 0]), 0);
+}
+
+#[test]
+fn transitions_need_the_number_of_states() {
+    assert_eq!(
+        "@RULE Empty\n@TABLE\nneighborhood:vonNeumann\n0,1,0,0,0,1\n".parse::<RuleTable>(),
+        Err(RuleTableError::MissingStateCount)
+    );
+    assert_eq!(
+        "@RULE Empty\n@TABLE\nneighborhood:vonNeumann\n".parse::<RuleTable>(),
+        Err(RuleTableError::MissingStateCount)
+    );
+}
+
+#[test]
+fn transitions_need_an_input_for_every_neighbor() {
+    assert_eq!(
+        "@RULE Short\n@TABLE\nn_states:2\nneighborhood:vonNeumann\n0,1,0,0,0,1\n0,1,0,0,1\n"
+            .parse::<RuleTable>(),
+        Err(RuleTableError::WrongInputCount(6))
+    );
+}
+
+#[test]
+fn unknown_variables_are_rejected() {
+    assert_eq!(
+        "@RULE Unknown\n@TABLE\nn_states:2\nneighborhood:vonNeumann\nvar a={0,1}\n0,a,x,0,0,1\n"
+            .parse::<RuleTable>(),
+        Err(RuleTableError::UnknownVariable(6, "x".to_string()))
+    );
+}
+
+#[test]
+fn every_state_a_cell_can_store_is_allowed() {
+    let table =
+        table("@RULE Full\n@TABLE\nn_states:256\nneighborhood:vonNeumann\n0,255,0,0,0,255\n");
+    assert_eq!(table.states, 256);
+    assert_eq!(table.next_cell(&[0, 255, 0, 0, 0]), 255);
+    assert_eq!(
+        "@RULE Over\n@TABLE\nn_states:257\n".parse::<RuleTable>(),
+        Err(RuleTableError::InvalidStateCount("257".to_string()))
+    );
+}
+
+#[test]
+fn symmetries_apply_to_a_neighborhood_given_after_them() {
+    // Hexagonal rules turn in sixths, which the default Moore neighborhood has no symmetries for
+    let table = table(
+        "@RULE Hex\n@TABLE\nn_states:2\nsymmetries:rotate6\nneighborhood:hexagonal\n0,1,0,0,0,0,0,1\n",
+    );
+    assert_eq!(table.neighborhood, TableNeighborhood::Hexagonal);
+    assert_eq!(table.next_cell(&[0, 1, 0, 0, 0, 0, 0]), 1);
+    assert_eq!(table.next_cell(&[0, 0, 0, 1, 0, 0, 0]), 1);
+    assert_eq!(table.next_cell(&[0, 1, 1, 0, 0, 0, 0]), 0);
+}
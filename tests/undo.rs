@@ -0,0 +1,156 @@
+//! Undoing edits, runs and loads the way the Bevy app records them
+
+use std::collections::BTreeSet;
+
+use game_of_life::rule::Rule;
+use game_of_life::state::{Coord, DenseGrid, GridSize, StateGrid};
+use game_of_life::topology::Topology;
+use game_of_life::undo::History;
+use game_of_life::Universe;
+
+const BLINKER: [(i64, i64); 3] = [(0, 1), (1, 1), (2, 1)];
+const GLIDER: [(i64, i64); 5] = [(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)];
+
+fn live_cells(universe: &Universe) -> BTreeSet<(i64, i64, u8)> {
+    universe
+        .cells()
+        .map(|(pos, state)| (pos.x, pos.y, state))
+        .collect()
+}
+
+fn place(universe: &mut Universe, cells: &[(i64, i64)], x: i64, y: i64) {
+    for (dx, dy) in cells {
+        universe.set(Coord::new(x + dx, y + dy), 1);
+    }
+}
+
+/// Steps like the app does, recording the cells each step flipped
+fn step(universe: &mut Universe, history: &mut History) {
+    if !history.records_step(universe.grid()) {
+        universe.step();
+        return;
+    }
+    let cells_before = universe.grid().cells();
+    let generation_before = universe.generation();
+    universe.step();
+    history.record_step(&cells_before, generation_before, universe.grid());
+}
+
+/// The undo memory of a run of 5 generations of a blinker on a square board, next to `blocks`
+/// blocks which never change
+fn run_memory(side: usize, blocks: i64) -> usize {
+    let mut universe = Universe::new(GridSize {
+        width: side,
+        height: side,
+    });
+    place(&mut universe, &BLINKER, 1, 1);
+    for block in 0..blocks {
+        place(
+            &mut universe,
+            &[(0, 0), (1, 0), (0, 1), (1, 1)],
+            6 + 3 * block,
+            6,
+        );
+    }
+    let mut history = History::new(1 << 20);
+    for _ in 0..5 {
+        step(&mut universe, &mut history);
+    }
+    history.memory_used()
+}
+
+#[test]
+fn run_memory_grows_with_the_changed_cells() {
+    let memory = run_memory(16, 0);
+    // Larger boards and more cells that don't change take no more memory
+    assert_eq!(run_memory(1024, 0), memory);
+    assert_eq!(run_memory(1024, 300), memory);
+    // A blinker flips 4 cells in every odd number of generations
+    let mut universe = Universe::new(GridSize::default());
+    place(&mut universe, &BLINKER, 1, 1);
+    let mut history = History::new(1 << 20);
+    step(&mut universe, &mut history);
+    let one_step = history.memory_used();
+    step(&mut universe, &mut history);
+    assert!(history.memory_used() < one_step);
+    step(&mut universe, &mut history);
+    assert_eq!(history.memory_used(), one_step);
+}
+
+#[test]
+fn runs_are_undone_and_redone_whole() {
+    let mut universe = Universe::new(GridSize {
+        width: 20,
+        height: 20,
+    });
+    place(&mut universe, &GLIDER, 5, 5);
+    let mut history = History::new(1 << 20);
+    let start = live_cells(&universe);
+    for _ in 0..3 {
+        step(&mut universe, &mut history);
+    }
+    let after_run = live_cells(&universe);
+    history.record_cell(Coord::new(0, 0), 0, 1, universe.generation());
+    universe.set(Coord::new(0, 0), 1);
+    history.end_stroke();
+    step(&mut universe, &mut history);
+    history.end_run();
+    step(&mut universe, &mut history);
+    assert_eq!(universe.generation(), 5);
+
+    assert!(history.undo(&mut universe));
+    assert_eq!(universe.generation(), 4);
+    assert!(history.undo(&mut universe));
+    assert_eq!(universe.generation(), 3);
+    assert!(history.undo(&mut universe));
+    assert_eq!(live_cells(&universe), after_run);
+    assert!(history.undo(&mut universe));
+    assert_eq!(live_cells(&universe), start);
+    assert_eq!(universe.generation(), 0);
+    assert!(!history.undo(&mut universe));
+
+    assert!(history.redo(&mut universe));
+    assert_eq!(live_cells(&universe), after_run);
+    assert_eq!(universe.generation(), 3);
+}
+
+#[test]
+fn runs_larger_than_the_memory_limit_are_forgotten() {
+    let mut universe = Universe::new(GridSize::default());
+    place(&mut universe, &GLIDER, 0, 0);
+    let mut history = History::new(0);
+    step(&mut universe, &mut history);
+    assert_eq!(history.memory_used(), 0);
+    assert!(!history.undo(&mut universe));
+    assert_eq!(universe.generation(), 1);
+}
+
+#[test]
+fn loads_swap_back_the_replaced_grid_rule_and_topology() {
+    let mut universe = Universe::new(GridSize {
+        width: 30,
+        height: 30,
+    });
+    place(&mut universe, &GLIDER, 3, 3);
+    let before = live_cells(&universe);
+    let mut history = History::new(1 << 20);
+    let rule = universe.rule().clone();
+    let topology = universe.topology();
+    let loaded = StateGrid::Bounded(DenseGrid::new(GridSize {
+        width: 10,
+        height: 12,
+    }));
+    let replaced = std::mem::replace(universe.grid_mut(), loaded);
+    universe.set_rule("B36/S23".parse::<Rule>().unwrap());
+    universe.set_topology(Topology::Plane);
+    history.record_load(replaced, rule, topology);
+
+    assert!(history.undo(&mut universe));
+    assert_eq!(live_cells(&universe), before);
+    assert_eq!(universe.rule(), &Rule::default());
+    assert_eq!(universe.topology(), Topology::Torus);
+    assert!(history.redo(&mut universe));
+    assert_eq!(universe.population(), 0);
+    assert_eq!(universe.rule().to_string(), "B36/S23");
+    assert_eq!(universe.topology(), Topology::Plane);
+}
@@ -0,0 +1,190 @@
+//! The stepping the Bevy app does every update, driven through the library
+
+use std::collections::BTreeSet;
+
+use game_of_life::rule::Rule;
+use game_of_life::state::{Coord, GridSize, StateGrid};
+use game_of_life::topology::Topology;
+use game_of_life::Universe;
+
+const GLIDER: [(i64, i64); 5] = [(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)];
+
+fn live_cells(universe: &Universe) -> BTreeSet<(i64, i64)> {
+    universe.cells().map(|(pos, _)| (pos.x, pos.y)).collect()
+}
+
+/// A square of random cells around `center`, the same ones on every run
+fn soup(universe: &mut Universe, center: Coord, size: i64) {
+    let mut seed = 0x2545_f491_4f6c_dd1d_u64;
+    for x in center.x - size / 2..center.x + size / 2 {
+        for y in center.y - size / 2..center.y + size / 2 {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            if seed % 3 == 0 {
+                universe.set(Coord::new(x, y), 1);
+            }
+        }
+    }
+}
+
+#[test]
+fn glider_wraps_around_the_torus() {
+    let mut universe = Universe::new(GridSize {
+        width: 12,
+        height: 12,
+    });
+    for (x, y) in GLIDER {
+        universe.set(Coord::new(x, y), 1);
+    }
+    let start = live_cells(&universe);
+    for generation in 1..48 {
+        universe.step();
+        assert_eq!(universe.population(), 5);
+        assert_eq!(universe.generation(), generation);
+    }
+    universe.step();
+    assert_eq!(live_cells(&universe), start);
+}
+
+#[test]
+fn steps_of_many_generations_compute_every_one() {
+    let size = GridSize {
+        width: 70,
+        height: 40,
+    };
+    let mut single = Universe::new(size);
+    soup(&mut single, Coord::new(35, 20), 30);
+    let mut batched = Universe::new(size);
+    soup(&mut batched, Coord::new(35, 20), 30);
+    for _ in 0..8 {
+        single.step();
+    }
+    batched.step_pow2(3);
+    assert_eq!(batched.generation(), 8);
+    assert_eq!(live_cells(&batched), live_cells(&single));
+}
+
+#[test]
+fn engines_agree() {
+    let size = GridSize {
+        width: 128,
+        height: 128,
+    };
+    let mut universes = [(); 3].map(|_| {
+        let mut universe = Universe::new(size);
+        universe.set_topology(Topology::Plane);
+        universe
+    });
+    // Bounded grid, hash set and HashLife
+    for (engine, universe) in universes.iter_mut().enumerate() {
+        for _ in 0..engine {
+            universe.next_engine(size);
+        }
+        soup(universe, Coord::new(64, 64), 16);
+    }
+    assert!(matches!(universes[2].grid(), StateGrid::HashLife(_)));
+    for universe in &mut universes {
+        universe.step_pow2(5);
+    }
+    let cells = live_cells(&universes[0]);
+    assert!(!cells.is_empty());
+    for universe in &universes {
+        assert_eq!(universe.generation(), 32);
+        assert_eq!(live_cells(universe), cells);
+    }
+}
+
+#[test]
+fn hashlife_hands_rules_it_cannot_run_to_the_hash_set() {
+    let size = GridSize::default();
+    let mut universe = Universe::new(size);
+    universe.next_engine(size);
+    universe.next_engine(size);
+    universe.set(Coord::new(0, 0), 1);
+    universe.set(Coord::new(1, 0), 1);
+    universe.set_rule("B2/S/C3".parse::<Rule>().unwrap());
+    universe.step();
+    assert!(matches!(universe.grid(), StateGrid::Unbounded(_)));
+    assert_eq!(universe.get(Coord::new(0, 0)), 2);
+    assert_eq!(universe.get(Coord::new(0, 1)), 1);
+}
+
+#[test]
+fn toggling_a_cell_in_its_state_kills_it() {
+    let mut universe = Universe::new(GridSize::default());
+    universe.toggle(Coord::new(3, 4), 1);
+    assert_eq!(universe.get(Coord::new(3, 4)), 1);
+    universe.toggle(Coord::new(3, 4), 1);
+    assert_eq!(universe.get(Coord::new(3, 4)), 0);
+    // Cells outside the bounded grid stay dead
+    universe.set(Coord::new(-1, 0), 1);
+    assert_eq!(universe.population(), 0);
+}
+
+#[test]
+fn rules_with_b0_only_run_on_the_bounded_grid() {
+    let size = GridSize {
+        width: 16,
+        height: 16,
+    };
+    for rulestring in ["B036/S23", "B0/S4V", "B02a/S23"] {
+        let rule = rulestring.parse::<Rule>().unwrap();
+        assert!(rule.births_in_empty_space(), "{rulestring}");
+        assert_eq!(rule.transition_table(), None, "{rulestring}");
+        let mut universe = Universe::new(size);
+        universe.set_rule(rule);
+        universe.next_engine(size);
+        assert!(matches!(universe.grid(), StateGrid::Bounded(_)));
+        // Empty space comes alive everywhere, not only around the live cells
+        universe.set(Coord::new(3, 3), 1);
+        universe.step();
+        assert!(universe.population() > 200, "{rulestring}");
+    }
+    assert!(!"B3/S23".parse::<Rule>().unwrap().births_in_empty_space());
+}
+
+#[test]
+fn unbounded_engines_do_not_step_rules_with_b0() {
+    let size = GridSize::default();
+    let mut universe = Universe::new(size);
+    universe.next_engine(size);
+    universe.next_engine(size);
+    assert!(matches!(universe.grid(), StateGrid::HashLife(_)));
+    universe.set(Coord::new(0, 0), 1);
+    universe.set_rule("B0/S".parse::<Rule>().unwrap());
+    universe.step();
+    assert_eq!(universe.generation(), 0);
+    assert_eq!(live_cells(&universe), BTreeSet::from([(0, 0)]));
+}
+
+#[test]
+fn larger_than_life_on_the_hash_set_steps_cells_far_apart() {
+    let rule: Rule = "R2,C0,M1,S4..8,B5..7,NM".parse().unwrap();
+    let size = GridSize {
+        width: 128,
+        height: 128,
+    };
+    let mut bounded = Universe::new(size);
+    bounded.set_topology(Topology::Plane);
+    bounded.set_rule(rule.clone());
+    soup(&mut bounded, Coord::new(64, 64), 16);
+    // The same soup twice, too far apart for one block of cells around both
+    let far = 1 << 40;
+    let mut sparse = Universe::new(size);
+    sparse.next_engine(size);
+    sparse.set_rule(rule);
+    soup(&mut sparse, Coord::new(64, 64), 16);
+    soup(&mut sparse, Coord::new(64 + far, 64 - far), 16);
+    for _ in 0..4 {
+        bounded.step();
+        sparse.step();
+    }
+    let cells = live_cells(&bounded);
+    assert!(!cells.is_empty());
+    let both: BTreeSet<(i64, i64)> = cells
+        .iter()
+        .flat_map(|(x, y)| [(*x, *y), (x + far, y - far)])
+        .collect();
+    assert_eq!(live_cells(&sparse), both);
+}